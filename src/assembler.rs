@@ -0,0 +1,452 @@
+//! Assembles Chip-8 programs from the mnemonics that the [disassembler](crate::disassembler)
+//! writes.
+//!
+//! Every line holds an instruction or a directive, optionally preceded by a `label:`, and
+//! anything after a `;` is a comment. Operands are numbers (`42`, `0x2A`, `0b101010`), labels
+//! and constants, which may be added and subtracted. `name = value` defines a constant, which
+//! can only refer to labels and constants defined before it. `db` and `dw` write bytes and
+//! big-endian words.
+//!
+//! ```
+//! use nibble8::assembler;
+//!
+//! let rom = assembler::assemble(
+//!     "
+//!     STEP = 2
+//! loop:
+//!     ADD V0, STEP ; count up
+//!     JP loop
+//!     db 0xF0, 0x90
+//!     ",
+//!     0x200,
+//! )?;
+//! assert_eq!(rom, [0x70, 0x02, 0x12, 0x00, 0xF0, 0x90]);
+//! # Ok::<(), nibble8::assembler::AssembleError>(())
+//! ```
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+use crate::machine::instruction::Instruction;
+
+/// What is wrong with a line of source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssembleError {
+    /// The line the error is on, counting from 1.
+    pub line: usize,
+    pub message: String,
+}
+
+impl Display for AssembleError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for AssembleError {}
+
+const MNEMONICS: [&str; 32] = [
+    "CLS", "RET", "SCD", "SCR", "SCL", "EXIT", "LOW", "HIGH", "JP", "CALL", "SE", "SNE", "SAVE",
+    "LOAD", "LD", "ADD", "OR", "AND", "XOR", "SUB", "SHR", "SUBN", "SHL", "RND", "DRW", "SKP",
+    "SKNP", "PLANE", "AUDIO", "PITCH", "DB", "DW",
+];
+
+// An operand, with any value in it still to be worked out once all labels are known.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operand<'a> {
+    V(u8),
+    I,
+    IndirectI,
+    DT,
+    ST,
+    K,
+    F,
+    HF,
+    B,
+    R,
+    Long(&'a str),
+    Value(&'a str),
+}
+
+impl<'a> Operand<'a> {
+    fn parse(text: &'a str) -> Self {
+        let upper = text.to_ascii_uppercase();
+        match upper.as_str() {
+            "I" => Operand::I,
+            "[I]" => Operand::IndirectI,
+            "DT" => Operand::DT,
+            "ST" => Operand::ST,
+            "K" => Operand::K,
+            "F" => Operand::F,
+            "HF" => Operand::HF,
+            "B" => Operand::B,
+            "R" => Operand::R,
+            _ if upper.len() == 2 && upper.starts_with('V') => {
+                match u8::from_str_radix(&upper[1..], 16) {
+                    Ok(x) => Operand::V(x),
+                    Err(_) => Operand::Value(text),
+                }
+            }
+            _ if upper.starts_with("LONG ") => Operand::Long(text[5..].trim()),
+            _ => Operand::Value(text),
+        }
+    }
+}
+
+// One instruction or directive, waiting for the values of its operands.
+struct Statement<'a> {
+    line: usize,
+    mnemonic: String,
+    operands: Vec<Operand<'a>>,
+}
+
+/// Assembles `source` into a program to be loaded at `origin`.
+///
+/// Stops at the first error, which says on which line it is.
+pub fn assemble(source: &str, origin: usize) -> Result<Vec<u8>, AssembleError> {
+    let mut symbols = HashMap::new();
+    let mut statements = vec![];
+    let mut address = origin;
+
+    // first pass: find out where every label is
+    for (i, text) in source.lines().enumerate() {
+        let line = i + 1;
+        let error = |message: String| AssembleError { line, message };
+        let mut text = text.split(';').next().unwrap_or("").trim();
+        if let Some((label, rest)) = text.split_once(':') {
+            if is_identifier(label.trim()) {
+                define(&mut symbols, label.trim(), address as i64).map_err(error)?;
+                text = rest.trim();
+            }
+        }
+        if let Some((name, value)) = text.split_once('=') {
+            let value = evaluate(value, &symbols).map_err(error)?;
+            define(&mut symbols, name.trim(), value).map_err(error)?;
+            continue;
+        }
+        if text.is_empty() {
+            continue;
+        }
+
+        let (mnemonic, operands) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+        let mnemonic = mnemonic.to_ascii_uppercase();
+        let operands: Vec<Operand> = if operands.trim().is_empty() {
+            vec![]
+        } else {
+            operands
+                .split(',')
+                .map(|o| Operand::parse(o.trim()))
+                .collect()
+        };
+        let size = match (mnemonic.as_str(), operands.as_slice()) {
+            ("DB", operands) => operands.len(),
+            ("DW", operands) => operands.len() * 2,
+            ("LD", [Operand::I, Operand::Long(_)]) => 4,
+            _ => 2,
+        };
+        statements.push(Statement {
+            line,
+            mnemonic,
+            operands,
+        });
+        address += size;
+    }
+
+    // second pass: encode everything, now that the labels are known
+    let mut rom = vec![];
+    for statement in statements {
+        let error = |message: String| AssembleError {
+            line: statement.line,
+            message,
+        };
+        let value = |operand: &Operand| match operand {
+            Operand::Value(text) => evaluate(text, &symbols),
+            _ => Err("expected a number, label or constant".to_string()),
+        };
+        match statement.mnemonic.as_str() {
+            "DB" | "DW" if statement.operands.is_empty() => {
+                return Err(error(format!(
+                    "{} needs at least one value",
+                    statement.mnemonic
+                )));
+            }
+            "DB" => {
+                for operand in &statement.operands {
+                    let byte = value(operand).and_then(as_byte).map_err(error)?;
+                    rom.push(byte);
+                }
+            }
+            "DW" => {
+                for operand in &statement.operands {
+                    let word = value(operand).and_then(as_word).map_err(error)?;
+                    rom.extend(word.to_be_bytes());
+                }
+            }
+            mnemonic => {
+                let instruction = encode(mnemonic, &statement.operands, &symbols).map_err(error)?;
+                rom.extend(instruction.encode());
+            }
+        }
+    }
+    Ok(rom)
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn define(symbols: &mut HashMap<String, i64>, name: &str, value: i64) -> Result<(), String> {
+    if !is_identifier(name) {
+        return Err(format!("`{}` is not a valid name", name));
+    }
+    if !matches!(Operand::parse(name), Operand::Value(_)) {
+        return Err(format!(
+            "`{}` is a register and cannot be used as a name",
+            name
+        ));
+    }
+    if symbols.insert(name.to_string(), value).is_some() {
+        return Err(format!("`{}` is already defined", name));
+    }
+    Ok(())
+}
+
+// Works out a sum or difference of numbers, labels and constants.
+fn evaluate(expression: &str, symbols: &HashMap<String, i64>) -> Result<i64, String> {
+    let mut total: i64 = 0;
+    let mut sign = 1;
+    let mut start = 0;
+    let ends = expression
+        .char_indices()
+        .filter(|(_, c)| *c == '+' || *c == '-')
+        .chain([(expression.len(), '+')]);
+    for (end, operator) in ends {
+        let term = expression[start..end].trim();
+        // only the first term may be left out, as in `-1`
+        if term.is_empty() && (start > 0 || end == expression.len()) {
+            return Err(format!("invalid expression `{}`", expression.trim()));
+        }
+        if !term.is_empty() {
+            total = term_value(term, symbols)?
+                .checked_mul(sign)
+                .and_then(|value| total.checked_add(value))
+                .ok_or_else(|| format!("value out of range in `{}`", expression.trim()))?;
+        }
+        sign = if operator == '-' { -1 } else { 1 };
+        start = end + 1;
+    }
+    Ok(total)
+}
+
+fn term_value(term: &str, symbols: &HashMap<String, i64>) -> Result<i64, String> {
+    let lower = term.to_ascii_lowercase();
+    let parsed = if let Some(hex) = lower.strip_prefix("0x") {
+        i64::from_str_radix(hex, 16)
+    } else if let Some(binary) = lower.strip_prefix("0b") {
+        i64::from_str_radix(binary, 2)
+    } else if term.starts_with(|c: char| c.is_ascii_digit()) {
+        term.parse()
+    } else {
+        return symbols
+            .get(term)
+            .copied()
+            .ok_or_else(|| format!("unknown label or constant `{}`", term));
+    };
+    parsed.map_err(|_| format!("invalid number `{}`", term))
+}
+
+fn as_byte(value: i64) -> Result<u8, String> {
+    if (-128..=0xFF).contains(&value) {
+        Ok(value as u8)
+    } else {
+        Err(format!("{} does not fit in a byte", value))
+    }
+}
+
+fn as_word(value: i64) -> Result<u16, String> {
+    if (-0x8000..=0xFFFF).contains(&value) {
+        Ok(value as u16)
+    } else {
+        Err(format!("{} does not fit in a word", value))
+    }
+}
+
+fn in_range(value: i64, max: usize) -> Result<usize, String> {
+    usize::try_from(value)
+        .ok()
+        .filter(|&value| value <= max)
+        .ok_or_else(|| format!("{} is out of range, expected 0 to {:#X}", value, max))
+}
+
+// Turns a mnemonic and its operands into the instruction they stand for.
+fn encode(
+    mnemonic: &str,
+    operands: &[Operand],
+    symbols: &HashMap<String, i64>,
+) -> Result<Instruction, String> {
+    use Operand::*;
+
+    let value = |text: &str| evaluate(text, symbols);
+    let byte = |text: &str| value(text).and_then(as_byte);
+    let nibble = |text: &str| value(text).and_then(|v| in_range(v, 0xF)).map(|n| n as u8);
+    let addr = |text: &str| value(text).and_then(|v| in_range(v, 0xFFF));
+    let long = |text: &str| value(text).and_then(|v| in_range(v, 0xFFFF));
+
+    let instruction = match (mnemonic, operands) {
+        ("CLS", []) => Instruction::_00E0,
+        ("RET", []) => Instruction::_00EE,
+        ("SCD", [Value(n)]) => Instruction::_00Cn(nibble(n)?),
+        ("SCR", []) => Instruction::_00FB,
+        ("SCL", []) => Instruction::_00FC,
+        ("EXIT", []) => Instruction::_00FD,
+        ("LOW", []) => Instruction::_00FE,
+        ("HIGH", []) => Instruction::_00FF,
+        ("JP", [Value(a)]) => Instruction::_1nnn(addr(a)?),
+        ("JP", [V(0), Value(a)]) => Instruction::_Bnnn(addr(a)?),
+        ("CALL", [Value(a)]) => Instruction::_2nnn(addr(a)?),
+        ("SE", [V(x), Value(kk)]) => Instruction::_3xkk(*x, byte(kk)?),
+        ("SE", [V(x), V(y)]) => Instruction::_5xy0(*x, *y),
+        ("SNE", [V(x), Value(kk)]) => Instruction::_4xkk(*x, byte(kk)?),
+        ("SNE", [V(x), V(y)]) => Instruction::_9xy0(*x, *y),
+        ("SAVE", [V(x), V(y)]) => Instruction::_5xy2(*x, *y),
+        ("LOAD", [V(x), V(y)]) => Instruction::_5xy3(*x, *y),
+        ("LD", [V(x), Value(kk)]) => Instruction::_6xkk(*x, byte(kk)?),
+        ("LD", [V(x), V(y)]) => Instruction::_8xy0(*x, *y),
+        ("LD", [I, Value(a)]) => Instruction::_Annn(addr(a)?),
+        ("LD", [I, Long(a)]) => Instruction::_F000(long(a)?),
+        ("LD", [V(x), DT]) => Instruction::_Fx07(*x),
+        ("LD", [V(x), K]) => Instruction::_Fx0A(*x),
+        ("LD", [DT, V(x)]) => Instruction::_Fx15(*x),
+        ("LD", [ST, V(x)]) => Instruction::_Fx18(*x),
+        ("LD", [F, V(x)]) => Instruction::_Fx29(*x),
+        ("LD", [HF, V(x)]) => Instruction::_Fx30(*x),
+        ("LD", [B, V(x)]) => Instruction::_Fx33(*x),
+        ("LD", [IndirectI, V(x)]) => Instruction::_Fx55(*x),
+        ("LD", [V(x), IndirectI]) => Instruction::_Fx65(*x),
+        ("LD", [R, V(x)]) => Instruction::_Fx75(*x),
+        ("LD", [V(x), R]) => Instruction::_Fx85(*x),
+        ("ADD", [V(x), Value(kk)]) => Instruction::_7xkk(*x, byte(kk)?),
+        ("ADD", [V(x), V(y)]) => Instruction::_8xy4(*x, *y),
+        ("ADD", [I, V(x)]) => Instruction::_Fx1E(*x),
+        ("OR", [V(x), V(y)]) => Instruction::_8xy1(*x, *y),
+        ("AND", [V(x), V(y)]) => Instruction::_8xy2(*x, *y),
+        ("XOR", [V(x), V(y)]) => Instruction::_8xy3(*x, *y),
+        ("SUB", [V(x), V(y)]) => Instruction::_8xy5(*x, *y),
+        ("SHR", [V(x), V(y)]) => Instruction::_8xy6(*x, *y),
+        ("SHR", [V(x)]) => Instruction::_8xy6(*x, *x),
+        ("SUBN", [V(x), V(y)]) => Instruction::_8xy7(*x, *y),
+        ("SHL", [V(x), V(y)]) => Instruction::_8xyE(*x, *y),
+        ("SHL", [V(x)]) => Instruction::_8xyE(*x, *x),
+        ("RND", [V(x), Value(kk)]) => Instruction::_Cxkk(*x, byte(kk)?),
+        ("DRW", [V(x), V(y), Value(n)]) => Instruction::_Dxyn(*x, *y, nibble(n)?),
+        ("SKP", [V(x)]) => Instruction::_Ex9E(*x),
+        ("SKNP", [V(x)]) => Instruction::_ExA1(*x),
+        ("PLANE", [Value(n)]) => Instruction::_Fn01(nibble(n)?),
+        ("AUDIO", []) => Instruction::_F002,
+        ("PITCH", [V(x)]) => Instruction::_Fx3A(*x),
+        _ if MNEMONICS.contains(&mnemonic) => {
+            return Err(format!("invalid operands for {}", mnemonic))
+        }
+        _ => return Err(format!("unknown instruction `{}`", mnemonic)),
+    };
+    Ok(instruction)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::disassembler;
+
+    fn error(source: &str) -> String {
+        assemble(source, 0x200).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn instructions() {
+        let rom = assemble(
+            "
+            ld va, 0x2A
+            DRW V0, V1, 5
+            LD [I], VE
+            LD I, LONG 0x1234
+            SHR V3
+            ADD V0, -1
+            ",
+            0x200,
+        )
+        .unwrap();
+        assert_eq!(
+            rom,
+            [0x6A, 0x2A, 0xD0, 0x15, 0xFE, 0x55, 0xF0, 0x00, 0x12, 0x34, 0x83, 0x36, 0x70, 0xFF]
+        );
+    }
+
+    #[test]
+    fn labels_and_constants() {
+        let rom = assemble(
+            "
+            SPRITE_HEIGHT = 2
+            start: LD I, sprite + 1
+                CALL draw
+            draw:
+                DRW V0, V1, SPRITE_HEIGHT
+                JP start
+            sprite:
+                dw 0xF090
+                db 0b11110000, SPRITE_HEIGHT
+            ",
+            0x200,
+        )
+        .unwrap();
+        assert_eq!(
+            rom,
+            [0xA2, 0x09, 0x22, 0x04, 0xD0, 0x12, 0x12, 0x00, 0xF0, 0x90, 0xF0, 0x02]
+        );
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(error("CLS\nFOO V1"), "line 2: unknown instruction `FOO`");
+        assert_eq!(error("LD V1, V2, V3"), "line 1: invalid operands for LD");
+        assert_eq!(
+            error("JP nowhere"),
+            "line 1: unknown label or constant `nowhere`"
+        );
+        assert_eq!(error("LD V0, 256"), "line 1: 256 does not fit in a byte");
+        assert_eq!(
+            error("JP 0x1000"),
+            "line 1: 4096 is out of range, expected 0 to 0xFFF"
+        );
+        assert_eq!(error("a:\n\na: CLS"), "line 3: `a` is already defined");
+        assert_eq!(
+            error("DT = 3"),
+            "line 1: `DT` is a register and cannot be used as a name"
+        );
+        assert_eq!(error("db 1 +"), "line 1: invalid expression `1 +`");
+        assert_eq!(error("CLS\ndb"), "line 2: DB needs at least one value");
+        assert_eq!(error("dw ; nothing"), "line 1: DW needs at least one value");
+        assert_eq!(
+            error("db 0x7FFFFFFFFFFFFFFF + 1"),
+            "line 1: value out of range in `0x7FFFFFFFFFFFFFFF + 1`"
+        );
+    }
+
+    #[test]
+    fn reassembles_disassembly() {
+        let rom = [
+            0xA2, 0x0C, 0x22, 0x08, 0x12, 0x04, 0xFF, 0xFF, 0xD0, 0x15, 0x00, 0xEE, 0xF0, 0x90,
+            0x90, 0x90, 0xF0,
+        ];
+        let mut source = vec![];
+        disassembler::trace(&rom, 0x200)
+            .write_source(&mut source)
+            .unwrap();
+        let source = String::from_utf8(source).unwrap();
+        assert_eq!(assemble(&source, 0x200).unwrap(), rom);
+    }
+}
@@ -0,0 +1,10 @@
+/// A frontend that can make the Chip-8 beep.
+pub trait Chip8Audio {
+    /// Called after every frame with whether the sound timer is running.
+    fn set_beeping(&mut self, beeping: bool);
+
+    /// Called when an XO-CHIP program loads an audio pattern (F002) or changes the pitch (Fx3A)
+    /// after having loaded a pattern. From then on, beeps should play `pattern` at
+    /// [`playback_rate(pitch)`](super::pattern::playback_rate) instead of a plain tone.
+    fn set_pattern(&mut self, _pattern: [u8; 16], _pitch: u8) {}
+}
@@ -0,0 +1,5 @@
+pub mod chip8_audio;
+pub mod null_audio;
+pub mod pattern;
+#[cfg(feature = "sdl")]
+pub mod sdl_audio;
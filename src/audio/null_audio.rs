@@ -0,0 +1,34 @@
+use super::chip8_audio::Chip8Audio;
+
+/// An audio backend that makes no sound, for running headless.
+#[derive(Debug, Default)]
+pub struct NullAudio {
+    beeping: bool,
+    pattern: Option<([u8; 16], u8)>,
+}
+
+impl NullAudio {
+    pub fn new() -> Self {
+        NullAudio::default()
+    }
+
+    /// Whether the machine was beeping as of the last frame.
+    pub fn is_beeping(&self) -> bool {
+        self.beeping
+    }
+
+    /// The last audio pattern and pitch set by the machine, if any.
+    pub fn pattern(&self) -> Option<([u8; 16], u8)> {
+        self.pattern
+    }
+}
+
+impl Chip8Audio for NullAudio {
+    fn set_beeping(&mut self, beeping: bool) {
+        self.beeping = beeping;
+    }
+
+    fn set_pattern(&mut self, pattern: [u8; 16], pitch: u8) {
+        self.pattern = Some((pattern, pitch));
+    }
+}
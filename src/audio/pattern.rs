@@ -0,0 +1,99 @@
+/// Bits in an XO-CHIP audio pattern.
+pub const PATTERN_BITS: usize = 16 * 8;
+
+/// The pitch at which a pattern plays back at 4000 bits per second.
+pub const DEFAULT_PITCH: u8 = 64;
+
+/// The rate, in bits per second, at which XO-CHIP plays back its audio pattern.
+pub fn playback_rate(pitch: u8) -> f32 {
+    4000.0 * 2f32.powf((pitch as f32 - 64.0) / 48.0)
+}
+
+/// Renders an XO-CHIP 1-bit audio pattern as PCM samples.
+///
+/// The 128 bits of the pattern are played back in a loop, most significant bit of the first byte
+/// first; a set bit is a positive sample, a cleared one a negative sample.
+#[derive(Debug, Clone)]
+pub struct PatternPlayer {
+    pattern: [u8; 16],
+    rate: f32,
+    // position in the pattern, in bits
+    position: f32,
+}
+
+impl PatternPlayer {
+    pub fn new(pattern: [u8; 16], pitch: u8) -> Self {
+        PatternPlayer {
+            pattern,
+            rate: playback_rate(pitch),
+            position: 0.0,
+        }
+    }
+
+    /// Switch to a new pattern and pitch, continuing from the current position.
+    pub fn set_pattern(&mut self, pattern: [u8; 16], pitch: u8) {
+        self.pattern = pattern;
+        self.rate = playback_rate(pitch);
+    }
+
+    /// Fill `samples` with the next stretch of the pattern, resampled to `sample_rate`.
+    pub fn fill(&mut self, samples: &mut [f32], sample_rate: f32, volume: f32) {
+        let step = self.rate / sample_rate;
+        for sample in samples {
+            let bit = self.position as usize;
+            let is_set = self.pattern[bit / 8] & (0b10000000 >> (bit % 8)) != 0;
+            *sample = if is_set { volume } else { -volume };
+            self.position = (self.position + step) % PATTERN_BITS as f32;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn rate() {
+        assert_eq!(playback_rate(DEFAULT_PITCH), 4000.0);
+        assert!((playback_rate(DEFAULT_PITCH + 48) - 8000.0).abs() < 0.01);
+        assert!((playback_rate(DEFAULT_PITCH - 48) - 2000.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn fill_at_playback_rate() {
+        let mut pattern = [0x00; 16];
+        pattern[0] = 0b10100000;
+        let mut player = PatternPlayer::new(pattern, DEFAULT_PITCH);
+
+        let mut samples = [0.0; 4];
+        player.fill(&mut samples, 4000.0, 0.5);
+        assert_eq!(samples, [0.5, -0.5, 0.5, -0.5]);
+    }
+
+    #[test]
+    fn fill_resamples() {
+        let mut pattern = [0x00; 16];
+        pattern[0] = 0b10100000;
+        let mut player = PatternPlayer::new(pattern, DEFAULT_PITCH);
+
+        // every bit lasts two samples at twice the playback rate
+        let mut samples = [0.0; 6];
+        player.fill(&mut samples, 8000.0, 1.0);
+        assert_eq!(samples, [1.0, 1.0, -1.0, -1.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn fill_loops() {
+        let mut pattern = [0x00; 16];
+        pattern[0] = 0b10000000;
+        pattern[15] = 0b00000001;
+        let mut player = PatternPlayer::new(pattern, DEFAULT_PITCH);
+
+        let mut samples = [0.0; PATTERN_BITS + 1];
+        player.fill(&mut samples, 4000.0, 1.0);
+        assert_eq!(samples[0], 1.0);
+        assert_eq!(samples[PATTERN_BITS - 1], 1.0);
+        assert_eq!(samples[PATTERN_BITS], 1.0);
+        assert_eq!(samples[1..PATTERN_BITS - 1], [-1.0; PATTERN_BITS - 2]);
+    }
+}
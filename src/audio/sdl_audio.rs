@@ -0,0 +1,86 @@
+use sdl2::{
+    audio::{AudioQueue, AudioSpecDesired},
+    Sdl,
+};
+
+use super::{chip8_audio::Chip8Audio, pattern::PatternPlayer};
+
+pub const DEFAULT_FREQUENCY: f32 = 440.0;
+pub const DEFAULT_VOLUME: f32 = 0.25;
+
+const SAMPLE_RATE: i32 = 44100;
+// how far ahead of playback the queue is kept filled while beeping, in seconds
+const BUFFER_AHEAD: f32 = 1.0 / 30.0;
+
+/// Plays a square wave, or the XO-CHIP audio pattern once one is loaded, through an SDL audio
+/// queue while the sound timer is running.
+pub struct SDLAudio {
+    queue: AudioQueue<f32>,
+    frequency: f32,
+    volume: f32,
+    phase: f32,
+    pattern: Option<PatternPlayer>,
+}
+
+impl SDLAudio {
+    /// `frequency` is the pitch of the beep in Hz; `volume` ranges from 0.0 to 1.0.
+    pub fn init(sdl_context: &Sdl, frequency: f32, volume: f32) -> Self {
+        let audio_subsystem = sdl_context.audio().unwrap();
+        let desired_spec = AudioSpecDesired {
+            freq: Some(SAMPLE_RATE),
+            channels: Some(1),
+            samples: None,
+        };
+        let queue = audio_subsystem
+            .open_queue::<f32, _>(None, &desired_spec)
+            .unwrap();
+        queue.resume();
+        SDLAudio {
+            queue,
+            frequency,
+            volume: volume.clamp(0.0, 1.0),
+            phase: 0.0,
+            pattern: None,
+        }
+    }
+}
+
+impl Chip8Audio for SDLAudio {
+    fn set_beeping(&mut self, beeping: bool) {
+        if !beeping {
+            self.queue.clear();
+            return;
+        }
+
+        let sample_rate = self.queue.spec().freq as f32;
+        let queued = self.queue.size() as usize / std::mem::size_of::<f32>();
+        let wanted = (sample_rate * BUFFER_AHEAD) as usize;
+        if queued >= wanted {
+            return;
+        }
+
+        let mut samples = vec![0.0; wanted - queued];
+        match self.pattern.as_mut() {
+            Some(pattern) => pattern.fill(&mut samples, sample_rate, self.volume),
+            None => {
+                let step = self.frequency / sample_rate;
+                for sample in samples.iter_mut() {
+                    *sample = if self.phase < 0.5 {
+                        self.volume
+                    } else {
+                        -self.volume
+                    };
+                    self.phase = (self.phase + step) % 1.0;
+                }
+            }
+        }
+        self.queue.queue(&samples);
+    }
+
+    fn set_pattern(&mut self, pattern: [u8; 16], pitch: u8) {
+        match self.pattern.as_mut() {
+            Some(player) => player.set_pattern(pattern, pitch),
+            None => self.pattern = Some(PatternPlayer::new(pattern, pitch)),
+        }
+    }
+}
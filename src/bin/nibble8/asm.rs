@@ -0,0 +1,38 @@
+use nibble8::{assembler, PROGRAM_OFFSET};
+use std::fs;
+use std::path::Path;
+use std::process::exit;
+
+// `nibble8 asm <source.asm> [-o <rom.ch8>]`: assembles a program, by default into a ROM next to
+// the source with the extension .ch8.
+pub fn run(program: &str, args: &[String]) {
+    let (source_path, rom_path) = match args {
+        [source] if !source.starts_with('-') => (source, Path::new(source).with_extension("ch8")),
+        [source, flag, rom] | [flag, rom, source] if flag == "-o" || flag == "--output" => {
+            (source, Path::new(rom).to_path_buf())
+        }
+        _ => {
+            eprintln!("Usage: {} asm <source.asm> [-o <rom.ch8>]", program);
+            exit(2);
+        }
+    };
+    let source = match fs::read_to_string(source_path) {
+        Ok(source) => source,
+        Err(e) => {
+            eprintln!("error: unable to read {}: {}", source_path, e);
+            exit(1);
+        }
+    };
+    let rom = match assembler::assemble(&source, PROGRAM_OFFSET) {
+        Ok(rom) => rom,
+        Err(e) => {
+            eprintln!("error: {}: {}", source_path, e);
+            exit(1);
+        }
+    };
+    if let Err(e) = fs::write(&rom_path, &rom) {
+        eprintln!("error: unable to write {}: {}", rom_path.display(), e);
+        exit(1);
+    }
+    println!("Wrote {} bytes to {}", rom.len(), rom_path.display());
+}
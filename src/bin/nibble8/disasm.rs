@@ -0,0 +1,45 @@
+use nibble8::{disassembler, PROGRAM_OFFSET};
+use std::fs;
+use std::io::{self, Write};
+use std::process::exit;
+
+// `nibble8 disasm [--source] <rom.ch8>`: prints the address, bytes and mnemonic of every word in
+// a ROM, or with --source traces its code and prints it as source that can be assembled again.
+pub fn run(program: &str, args: &[String]) {
+    let (source, path) = match args {
+        [flag, path] if flag == "--source" => (true, path),
+        [path] if !path.starts_with('-') => (false, path),
+        _ => {
+            eprintln!("Usage: {} disasm [--source] <rom.ch8>", program);
+            exit(2);
+        }
+    };
+    let rom = match fs::read(path) {
+        Ok(rom) => rom,
+        Err(e) => {
+            eprintln!("error: unable to read {}: {}", path, e);
+            exit(1);
+        }
+    };
+    let mut out = io::stdout().lock();
+    let written = if !source {
+        disassembler::write_listing(&rom, PROGRAM_OFFSET, &mut out)
+    } else {
+        let disassembly = disassembler::trace(&rom, PROGRAM_OFFSET);
+        for address in disassembly.unresolved() {
+            eprintln!(
+                "warning: computed jump at {:#05X} not followed; the code it reaches is shown as data",
+                address
+            );
+        }
+        disassembly.write_source(&mut out)
+    };
+    if let Err(e) = written {
+        // a closed pipe, e.g. when piped into `head`, is not worth complaining about
+        if e.kind() != io::ErrorKind::BrokenPipe {
+            eprintln!("error: {}", e);
+            exit(1);
+        }
+    }
+    let _ = out.flush();
+}
@@ -0,0 +1,148 @@
+use nibble8::golden::{self, GoldenError, Reference};
+use nibble8::recorder::Recorder;
+use nibble8::screenshot;
+use nibble8::Chip8;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::Path;
+use std::process::exit;
+
+use crate::Options;
+
+// Runs the ROM without a window until it exits, gets stuck in a jump to itself (the usual way
+// for a test ROM to signal it is done), or has run for the requested number of frames. The
+// final state of the machine is then written to stdout or the output file, and the final frame
+// to the screenshot file if one was given. With --record, every frame is recorded as well.
+//
+// Keys are pressed and released as scripted in the --input file, and with --expect the final
+// frame is compared against a reference image, failing the run if they differ.
+pub fn run(options: &Options) {
+    let input = match &options.input {
+        Some(path) => match fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|script| golden::parse_input(&script))
+        {
+            Ok(input) => input,
+            Err(e) => {
+                eprintln!("Unable to read {}: {}", path, e);
+                exit(1);
+            }
+        },
+        None => vec![],
+    };
+
+    // the recorder draws nothing unless it is started, so it doubles as a null display
+    let mut recorder = Recorder::new(options.palette);
+    if options.record.is_some() {
+        recorder.start();
+    }
+    let mut chip8 = Chip8::with_quirks(&mut recorder, options.quirks);
+    options.configure(&mut chip8);
+    if let Err(e) = options.load_rom(&mut chip8) {
+        eprintln!("Unable to load {}: {}", options.rom, e);
+        exit(1);
+    }
+
+    let mut frames = 0;
+    let mut error = None;
+    while frames < options.frames && !chip8.is_halted() && !is_stuck(&chip8) {
+        golden::apply_input(&mut chip8, &input, frames);
+        if let Err(e) = chip8.run_frame() {
+            error = Some(e);
+            break;
+        }
+        frames += 1;
+    }
+
+    let written = match &options.output {
+        Some(path) => File::create(path).and_then(|mut file| dump(&mut file, &chip8, frames)),
+        None => dump(&mut io::stdout().lock(), &chip8, frames),
+    };
+    if let Err(e) = written {
+        eprintln!("Unable to write state: {}", e);
+        exit(1);
+    }
+    if let Some(path) = &options.screenshot {
+        let saved = screenshot::save(
+            chip8.display_state(),
+            Path::new(path),
+            options.scale,
+            &options.palette,
+        );
+        if let Err(e) = saved {
+            eprintln!("Unable to save {}: {}", path, e);
+            exit(1);
+        }
+    }
+    let mut failed = false;
+    if let Some(path) = &options.expect {
+        let path = Path::new(path);
+        let diff = match &options.diff {
+            Some(diff) => diff.into(),
+            None => path.with_extension("diff.png"),
+        };
+        let compared = Reference::load(path, &options.palette)
+            .map_err(Into::into)
+            .and_then(|reference| reference.compare(chip8.display_state(), Some(&diff)));
+        if let Err(e) = compared {
+            eprintln!("{}: {}", path.display(), e);
+            if let GoldenError::Mismatch { .. } = e {
+                eprintln!("Wrote differences to {}", diff.display());
+            }
+            failed = true;
+        }
+    }
+    if let Some(path) = &options.record {
+        if let Err(e) = recorder.save(Path::new(path), options.scale) {
+            eprintln!("Unable to save {}: {}", path, e);
+            exit(1);
+        }
+    }
+    if let Some(e) = error {
+        eprintln!("{}", e);
+        exit(1);
+    }
+    if failed {
+        exit(1);
+    }
+}
+
+// Whether the next instruction is a jump to its own address.
+fn is_stuck(chip8: &Chip8) -> bool {
+    let pc = chip8.program_counter();
+    match chip8.ram().read_bytes(pc, 2) {
+        Ok(&[high, low]) => high >> 4 == 0x1 && ((high as usize & 0x0F) << 8 | low as usize) == pc,
+        _ => false,
+    }
+}
+
+fn dump(out: &mut dyn Write, chip8: &Chip8, frames: u64) -> io::Result<()> {
+    writeln!(out, "frames: {}", frames)?;
+    writeln!(out, "halted: {}", chip8.is_halted())?;
+    writeln!(out, "PC: {:#06X}", chip8.program_counter())?;
+    write!(out, "{:?}", chip8.registers())?;
+    writeln!(out, "stack: {:?}", chip8.stack())?;
+    writeln!(out)?;
+    write!(out, "{}", chip8.display_state())?;
+    writeln!(out)?;
+    dump_memory(out, chip8)
+}
+
+const DUMP_PER_LINE: usize = 32;
+
+// Writes memory a row at a time with its address, skipping rows that are all zero, as most of
+// it is usually unused.
+fn dump_memory(out: &mut dyn Write, chip8: &Chip8) -> io::Result<()> {
+    writeln!(out, "memory:")?;
+    let memory = chip8
+        .ram()
+        .read_bytes(0, chip8.ram().size())
+        .map_err(|e| io::Error::other(e.to_string()))?;
+    for (row, bytes) in memory.chunks(DUMP_PER_LINE).enumerate() {
+        if bytes.iter().any(|&byte| byte != 0) {
+            let hex: Vec<String> = bytes.iter().map(|b| format!("{:02X}", b)).collect();
+            writeln!(out, "{:#06X}  {}", row * DUMP_PER_LINE, hex.join(" "))?;
+        }
+    }
+    Ok(())
+}
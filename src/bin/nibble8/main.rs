@@ -0,0 +1,485 @@
+use nibble8::display::phosphor::Persistence;
+use nibble8::display::terminal_display::Glyphs;
+use nibble8::keymap::Keymap;
+use nibble8::octo;
+use nibble8::{Chip8, Palette, Platform, Quirks, DEFAULT_IPF, PROGRAM_OFFSET};
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process::exit;
+use std::str::FromStr;
+
+mod asm;
+mod disasm;
+mod headless;
+#[cfg(feature = "sdl")]
+mod sdl;
+#[cfg(feature = "terminal")]
+mod terminal;
+
+// the number of frames a headless run lasts, unless it ends earlier
+const DEFAULT_HEADLESS_FRAMES: u64 = 600;
+// how many screen pixels wide and high a low-res pixel is in a new window, a screenshot or a
+// recording
+const DEFAULT_SCALE: usize = 10;
+
+struct Options {
+    rom: String,
+    quirks: Quirks,
+    ipf: usize,
+    seed: Option<u64>,
+    start_address: usize,
+    #[cfg_attr(not(feature = "sdl"), allow(dead_code))]
+    mute: bool,
+    #[cfg_attr(not(any(feature = "sdl", feature = "terminal")), allow(dead_code))]
+    keymap: Keymap,
+    palette: Palette,
+    #[cfg_attr(not(feature = "sdl"), allow(dead_code))]
+    persistence: Persistence,
+    #[cfg_attr(not(feature = "sdl"), allow(dead_code))]
+    volume: Option<f32>,
+    #[cfg_attr(not(feature = "sdl"), allow(dead_code))]
+    tone: Option<f32>,
+    #[cfg_attr(not(feature = "sdl"), allow(dead_code))]
+    debug: bool,
+    headless: bool,
+    terminal: bool,
+    #[cfg_attr(not(feature = "terminal"), allow(dead_code))]
+    glyphs: Glyphs,
+    frames: u64,
+    output: Option<String>,
+    screenshot: Option<String>,
+    scale: usize,
+    record: Option<String>,
+    input: Option<String>,
+    expect: Option<String>,
+    diff: Option<String>,
+}
+
+impl Options {
+    // Applies the machine settings that are not passed to the constructor.
+    fn configure(&self, chip8: &mut Chip8) {
+        chip8.set_ipf(self.ipf);
+        chip8.set_start_address(self.start_address);
+        if let Some(seed) = self.seed {
+            chip8.set_seed(seed);
+        }
+    }
+
+    // Loads the ROM into the machine, compiling it first if it is Octo source (`.8o`).
+    fn load_rom(&self, chip8: &mut Chip8) -> Result<(), String> {
+        if Path::new(&self.rom)
+            .extension()
+            .is_some_and(|ext| ext == "8o")
+        {
+            let source = fs::read_to_string(&self.rom).map_err(|e| e.to_string())?;
+            let rom = octo::compile(&source, self.start_address).map_err(|e| e.to_string())?;
+            chip8.load_rom_bytes(&rom).map_err(|e| e.to_string())
+        } else {
+            chip8.load_rom(&self.rom).map_err(|e| e.to_string())
+        }
+    }
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 2 {
+        eprint!("{}", usage(&args[0]));
+        exit(2);
+    }
+    match args[1].as_str() {
+        "asm" => return asm::run(&args[0], &args[2..]),
+        "disasm" => return disasm::run(&args[0], &args[2..]),
+        _ => {}
+    }
+    let options = match parse_args(&args[1..]) {
+        Ok(Some(options)) => options,
+        Ok(None) => {
+            print!("{}", usage(&args[0]));
+            return;
+        }
+        Err(e) => {
+            eprintln!("error: {}", e);
+            eprintln!("Run `{} --help` for usage.", args[0]);
+            exit(2);
+        }
+    };
+    if let Err(e) = check_rom(&options.rom) {
+        eprintln!("error: {}", e);
+        exit(1);
+    }
+
+    if options.headless {
+        headless::run(&options);
+        return;
+    }
+    if options.terminal {
+        #[cfg(feature = "terminal")]
+        terminal::run(&options);
+        #[cfg(not(feature = "terminal"))]
+        eprintln!("error: nibble8 was built without the terminal feature");
+        return;
+    }
+
+    #[cfg(feature = "sdl")]
+    sdl::run(&options);
+    #[cfg(not(feature = "sdl"))]
+    eprintln!("error: nibble8 was built without the sdl feature; run with --headless");
+}
+
+fn usage(program: &str) -> String {
+    format!(
+        "Usage: {program} [options] <rom.ch8|program.8o>
+       {program} asm <source.asm> [-o <rom.ch8>]
+       {program} disasm [--source] <rom.ch8>
+
+Machine:
+  --platform <{platforms}>
+                          use the quirks and speed of a Chip-8 variant
+  --quirks <{quirks}>
+                          interpret ambiguous opcodes like this interpreter (overrides --platform)
+  --ipf <n>               instructions per 60 Hz frame (default {ipf}; overrides --platform)
+  --start-address <addr>  load the ROM at this address (default {start:#X})
+  --seed <n>              seed the random number generator, to repeat runs exactly
+
+Frontend:
+  --keymap <{keymaps}|file>
+                          keyboard layout, or a file mapping host keys to Chip-8 keys
+  --palette <{palettes}|colours>
+                          colour theme, or colours as RRGGBB,RRGGBB[,RRGGBB,RRGGBB]
+  --persistence <{persistence}>
+                          keep pixels visible after they turn off, to reduce flicker
+  --scale <n>             initial window size, and size of screenshots and recordings, in screen
+                          pixels per Chip-8 pixel (default {scale})
+  --mute                  do not play sound
+  --volume <0.0-1.0>      how loud to beep, from silent to full volume
+  --tone <Hz>             the pitch to beep at
+  --debug                 start paused, and step through the program from a prompt
+  --terminal              draw in the terminal instead of a window
+  --glyphs <{glyphs}>
+                          characters to draw with in the terminal
+
+Headless:
+  --headless              run without a window and print the final state
+  --frames <n>            stop after this many frames (default {frames})
+  --output <file>         write the final state to a file instead of stdout
+  --screenshot <file>     save the final frame as .png, .pbm or .pgm
+  --record <file.gif|dir> record every frame as a GIF, or as PNGs in a directory
+  --input <file>          press and release keys as scripted in the file
+  --expect <file.png>     fail unless the final frame matches this reference image
+  --diff <file.png>       where to write the differences from the reference
+
+  -h, --help              show this help
+",
+        program = program,
+        platforms = Platform::PRESETS.join("|"),
+        quirks = Quirks::PRESETS.join("|"),
+        ipf = DEFAULT_IPF,
+        start = PROGRAM_OFFSET,
+        keymaps = Keymap::PRESETS.join("|"),
+        palettes = Palette::PRESETS.join("|"),
+        persistence = Persistence::PRESETS.join("|"),
+        scale = DEFAULT_SCALE,
+        glyphs = Glyphs::PRESETS.join("|"),
+        frames = DEFAULT_HEADLESS_FRAMES,
+    )
+}
+
+// Returns None if help was asked for.
+fn parse_args(args: &[String]) -> Result<Option<Options>, String> {
+    let mut rom = None;
+    let mut platform: Option<Platform> = None;
+    let mut quirks = None;
+    let mut ipf = None;
+    let mut seed = None;
+    let mut start_address = PROGRAM_OFFSET;
+    let mut mute = false;
+    let mut keymap = Keymap::default();
+    let mut palette = Palette::default();
+    let mut persistence = Persistence::Off;
+    let mut volume = None;
+    let mut tone = None;
+    let mut debug = false;
+    let mut headless = false;
+    let mut terminal = false;
+    let mut glyphs = Glyphs::HalfBlocks;
+    let mut frames = DEFAULT_HEADLESS_FRAMES;
+    let mut output = None;
+    let mut screenshot = None;
+    let mut scale = DEFAULT_SCALE;
+    let mut record = None;
+    let mut input = None;
+    let mut expect = None;
+    let mut diff = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let flag = arg.as_str();
+        match flag {
+            "-h" | "--help" => return Ok(None),
+            "--platform" => platform = Some(value(&mut args, flag)?.parse()?),
+            "--quirks" => quirks = Some(value(&mut args, flag)?.parse()?),
+            "--ipf" => ipf = Some(positive(value(&mut args, flag)?, flag)?),
+            "--seed" => seed = Some(number(value(&mut args, flag)?, flag)?),
+            "--start-address" => {
+                let address = value(&mut args, flag)?;
+                let parsed = match address.strip_prefix("0x") {
+                    Some(hex) => usize::from_str_radix(hex, 16).ok(),
+                    None => address.parse().ok(),
+                };
+                start_address = parsed
+                    .filter(|&address| address < 0x10000)
+                    .ok_or_else(|| format!("invalid value '{}' for {}", address, flag))?;
+            }
+            "--mute" => mute = true,
+            "--keymap" => {
+                let name = value(&mut args, flag)?;
+                keymap = name.parse().or_else(|_| Keymap::load(name))?;
+            }
+            "--palette" => palette = value(&mut args, flag)?.parse()?,
+            "--persistence" => persistence = value(&mut args, flag)?.parse()?,
+            "--volume" => {
+                let level = value(&mut args, flag)?;
+                volume = Some(
+                    number(level, flag)
+                        .ok()
+                        .filter(|level| (0.0..=1.0).contains(level))
+                        .ok_or_else(|| {
+                            format!("invalid value '{}' for {}, expected 0.0 - 1.0", level, flag)
+                        })?,
+                );
+            }
+            "--tone" => tone = Some(positive(value(&mut args, flag)?, flag)?),
+            "--scale" => scale = positive(value(&mut args, flag)?, flag)?,
+            "--debug" => debug = true,
+            "--headless" => headless = true,
+            "--terminal" => terminal = true,
+            "--glyphs" => glyphs = value(&mut args, flag)?.parse()?,
+            "--frames" => frames = number(value(&mut args, flag)?, flag)?,
+            "--output" => output = Some(value(&mut args, flag)?.to_string()),
+            "--screenshot" => screenshot = Some(value(&mut args, flag)?.to_string()),
+            "--record" => record = Some(value(&mut args, flag)?.to_string()),
+            "--input" => input = Some(value(&mut args, flag)?.to_string()),
+            "--expect" => expect = Some(value(&mut args, flag)?.to_string()),
+            "--diff" => diff = Some(value(&mut args, flag)?.to_string()),
+            _ if flag.starts_with('-') => return Err(format!("unknown option '{}'", flag)),
+            _ if rom.is_some() => return Err(format!("unexpected argument '{}'", flag)),
+            path => rom = Some(path.to_string()),
+        }
+    }
+    if headless && terminal {
+        return Err("--headless and --terminal cannot be used together".to_string());
+    }
+    if debug && (headless || terminal) {
+        return Err(
+            "--debug needs the window; it cannot be used with --headless or --terminal".to_string(),
+        );
+    }
+
+    let rom = rom.ok_or("no ROM given")?;
+    Ok(Some(Options {
+        rom,
+        quirks: quirks
+            .or(platform.map(Platform::quirks))
+            .unwrap_or_default(),
+        ipf: ipf.or(platform.map(Platform::ipf)).unwrap_or(DEFAULT_IPF),
+        seed,
+        start_address,
+        mute,
+        keymap,
+        palette,
+        persistence,
+        volume,
+        tone,
+        debug,
+        headless,
+        terminal,
+        glyphs,
+        frames,
+        output,
+        screenshot,
+        scale,
+        record,
+        input,
+        expect,
+        diff,
+    }))
+}
+
+// The value following a flag.
+fn value<'a>(args: &mut impl Iterator<Item = &'a String>, flag: &str) -> Result<&'a str, String> {
+    args.next()
+        .map(|value| value.as_str())
+        .ok_or_else(|| format!("{} needs a value", flag))
+}
+
+fn number<T: FromStr>(value: &str, flag: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("invalid value '{}' for {}", value, flag))
+}
+
+fn positive<T: FromStr + Default + PartialOrd>(value: &str, flag: &str) -> Result<T, String> {
+    number(value, flag)
+        .ok()
+        .filter(|n| *n > T::default())
+        .ok_or_else(|| {
+            format!(
+                "invalid value '{}' for {}, expected a positive number",
+                value, flag
+            )
+        })
+}
+
+// Explains what is wrong with the ROM path, if anything, before any window opens.
+fn check_rom(path: &str) -> Result<(), String> {
+    match fs::metadata(path) {
+        Ok(metadata) if metadata.is_dir() => Err(format!("{} is a directory, not a ROM", path)),
+        Ok(metadata) if metadata.len() == 0 => Err(format!("{} is empty", path)),
+        Ok(_) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            Err(format!("ROM not found: {}", path))
+        }
+        Err(e) => Err(format!("unable to read {}: {}", path, e)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse(args: &str) -> Result<Options, String> {
+        let args: Vec<String> = args.split_whitespace().map(String::from).collect();
+        parse_args(&args).map(|options| options.expect("no help was asked for"))
+    }
+
+    fn error(args: &str) -> String {
+        parse(args).err().expect("expected an error")
+    }
+
+    #[test]
+    fn platform_defaults() {
+        let options = parse("--platform schip game.ch8").unwrap();
+        assert_eq!(options.rom, "game.ch8");
+        assert_eq!(options.quirks, Quirks::SUPER_CHIP);
+        assert_eq!(options.ipf, Platform::SuperChip.ipf());
+
+        // --quirks and --ipf win over the platform, whichever comes first
+        let options = parse("--quirks modern --platform chip8 --ipf 50 game.ch8").unwrap();
+        assert_eq!(options.quirks, Quirks::MODERN);
+        assert_eq!(options.ipf, 50);
+        let options = parse("--platform chip8 --quirks vip game.ch8").unwrap();
+        assert_eq!(options.quirks, Quirks::COSMAC_VIP);
+        assert_eq!(options.ipf, DEFAULT_IPF);
+    }
+
+    #[test]
+    fn start_address() {
+        assert_eq!(parse("game.ch8").unwrap().start_address, PROGRAM_OFFSET);
+        assert_eq!(
+            parse("--start-address 0x600 game.ch8")
+                .unwrap()
+                .start_address,
+            0x600
+        );
+        assert_eq!(
+            parse("--start-address 1536 game.ch8")
+                .unwrap()
+                .start_address,
+            0x600
+        );
+        assert_eq!(
+            parse("--start-address 0xFFFF game.ch8")
+                .unwrap()
+                .start_address,
+            0xFFFF
+        );
+        assert_eq!(
+            error("--start-address 0x10000 game.ch8"),
+            "invalid value '0x10000' for --start-address"
+        );
+        assert_eq!(
+            error("--start-address 0xZZ game.ch8"),
+            "invalid value '0xZZ' for --start-address"
+        );
+    }
+
+    #[test]
+    fn errors() {
+        assert!(parse_args(&["--help".to_string()]).unwrap().is_none());
+        assert_eq!(error("game.ch8 --ipf"), "--ipf needs a value");
+        assert_eq!(error("--fast game.ch8"), "unknown option '--fast'");
+        assert_eq!(
+            error("game.ch8 other.ch8"),
+            "unexpected argument 'other.ch8'"
+        );
+        assert_eq!(error("--mute"), "no ROM given");
+        assert!(error("--ipf 0 game.ch8").contains("expected a positive number"));
+    }
+
+    #[test]
+    fn conflicts() {
+        assert_eq!(
+            error("--headless --terminal game.ch8"),
+            "--headless and --terminal cannot be used together"
+        );
+        for mode in ["--headless", "--terminal"] {
+            assert_eq!(
+                error(&format!("--debug {} game.ch8", mode)),
+                "--debug needs the window; it cannot be used with --headless or --terminal"
+            );
+        }
+        assert!(parse("--debug game.ch8").unwrap().debug);
+    }
+
+    #[test]
+    fn sound_and_scale() {
+        let options = parse("--volume 0.5 --tone 220 --scale 4 game.ch8").unwrap();
+        assert_eq!(options.volume, Some(0.5));
+        assert_eq!(options.tone, Some(220.0));
+        assert_eq!(options.scale, 4);
+        let options = parse("game.ch8").unwrap();
+        assert_eq!((options.volume, options.tone), (None, None));
+        assert_eq!(options.scale, DEFAULT_SCALE);
+
+        assert_eq!(
+            error("--volume 1.5 game.ch8"),
+            "invalid value '1.5' for --volume, expected 0.0 - 1.0"
+        );
+        assert!(error("--tone 0 game.ch8").contains("expected a positive number"));
+        assert!(error("--scale 0 game.ch8").contains("expected a positive number"));
+    }
+
+    #[test]
+    fn check_rom_path() {
+        let dir = env::temp_dir().join(format!("nibble8-check-rom-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let empty = dir.join("empty.ch8");
+        let rom = dir.join("rom.ch8");
+        fs::write(&empty, []).unwrap();
+        fs::write(&rom, [0x12, 0x00]).unwrap();
+        let missing = dir.join("missing.ch8");
+        let (dir_path, empty, rom, missing) = (
+            dir.to_str().unwrap().to_string(),
+            empty.to_str().unwrap().to_string(),
+            rom.to_str().unwrap().to_string(),
+            missing.to_str().unwrap().to_string(),
+        );
+
+        let results = [
+            check_rom(&dir_path),
+            check_rom(&empty),
+            check_rom(&rom),
+            check_rom(&missing),
+        ];
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(
+            results,
+            [
+                Err(format!("{} is a directory, not a ROM", dir_path)),
+                Err(format!("{} is empty", empty)),
+                Ok(()),
+                Err(format!("ROM not found: {}", missing)),
+            ]
+        );
+    }
+}
@@ -0,0 +1,211 @@
+use nibble8::audio::sdl_audio::{SDLAudio, DEFAULT_FREQUENCY, DEFAULT_VOLUME};
+use nibble8::debugger::Debugger;
+use nibble8::display::sdl_display::SDLDisplay;
+use nibble8::frame_timer::{FrameTimer, FRAME_RATE};
+use nibble8::recorder::Recorder;
+use nibble8::screenshot;
+use nibble8::{Chip8, Palette};
+use sdl2::event::{Event, WindowEvent};
+use sdl2::keyboard::{Keycode, Mod};
+use std::cell::RefCell;
+use std::io::{stdin, stdout};
+use std::path::Path;
+use std::rc::Rc;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::Options;
+
+pub fn run(options: &Options) {
+    let keymap = &options.keymap;
+    let sdl_context = sdl2::init().unwrap();
+    // shared with the machine, to change the palette and window while it runs
+    let display = Rc::new(RefCell::new(SDLDisplay::init(
+        &sdl_context,
+        options.scale,
+        options.palette,
+    )));
+    display.borrow_mut().set_persistence(options.persistence);
+    let mut audio = SDLAudio::init(
+        &sdl_context,
+        options.tone.unwrap_or(DEFAULT_FREQUENCY),
+        options.volume.unwrap_or(DEFAULT_VOLUME),
+    );
+    let mut machine_display = Rc::clone(&display);
+    let mut chip8 = Chip8::with_quirks(&mut machine_display, options.quirks);
+    options.configure(&mut chip8);
+    if !options.mute {
+        chip8.set_audio(&mut audio);
+    }
+
+    if let Err(e) = options.load_rom(&mut chip8) {
+        eprintln!("Unable to load {}: {}", options.rom, e);
+        return;
+    }
+    let mut event_pump = sdl_context.event_pump().unwrap();
+    let mut recorder = Recorder::new(options.palette);
+    let mut debugger = options.debug.then(|| (Debugger::new(), read_commands()));
+    if let Some((debugger, _)) = &debugger {
+        let _ = debugger.start(&chip8, &mut stdout());
+    }
+
+    let mut frame_timer = FrameTimer::new(FRAME_RATE);
+    'running: loop {
+        for event in event_pump.poll_iter() {
+            match event {
+                Event::Quit { .. }
+                | Event::KeyDown {
+                    keycode: Some(Keycode::Escape),
+                    ..
+                } => break 'running,
+                Event::KeyDown {
+                    keycode: Some(Keycode::F12),
+                    repeat: false,
+                    ..
+                } => save_screenshot(&chip8, options.scale, display.borrow().palette()),
+                Event::KeyDown {
+                    keycode: Some(Keycode::F11),
+                    repeat: false,
+                    ..
+                } => display.borrow_mut().toggle_fullscreen(),
+                Event::KeyDown {
+                    keycode: Some(Keycode::Return),
+                    keymod,
+                    repeat: false,
+                    ..
+                } if keymod.intersects(Mod::LALTMOD | Mod::RALTMOD) => {
+                    display.borrow_mut().toggle_fullscreen()
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::F9),
+                    repeat: false,
+                    ..
+                } => {
+                    let mut display = display.borrow_mut();
+                    let palette = display.palette().next_preset();
+                    display.set_palette(palette);
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::F10),
+                    repeat: false,
+                    ..
+                } => toggle_recording(&mut recorder, options.scale, display.borrow().palette()),
+                Event::KeyDown {
+                    keycode: Some(keycode),
+                    repeat: false,
+                    ..
+                } => {
+                    if let Some(key) = keymap.get(&keycode.name()) {
+                        chip8.press_key(key);
+                    }
+                }
+                Event::KeyUp {
+                    keycode: Some(keycode),
+                    ..
+                } => {
+                    if let Some(key) = keymap.get(&keycode.name()) {
+                        chip8.release_key(key);
+                    }
+                }
+                Event::Window {
+                    win_event: WindowEvent::FocusLost,
+                    ..
+                } => chip8.clear_keys(),
+                Event::Window {
+                    win_event: WindowEvent::SizeChanged(..) | WindowEvent::Exposed,
+                    ..
+                } => display.borrow_mut().redraw(),
+                _ => {}
+            }
+        }
+        if let Some((debugger, commands)) = &mut debugger {
+            loop {
+                let command = match commands.try_recv() {
+                    Ok(command) => command,
+                    Err(TryRecvError::Empty) => break,
+                    // stdin was closed
+                    Err(TryRecvError::Disconnected) => break 'running,
+                };
+                match debugger.execute(&command, &mut chip8, &mut stdout()) {
+                    Ok(true) => {}
+                    Ok(false) | Err(_) => break 'running,
+                }
+            }
+        }
+        for _ in 0..frame_timer.frames_due() {
+            if let Some((debugger, _)) = &mut debugger {
+                // frames are only recorded while the program runs, not while it is paused
+                if !debugger.is_paused() {
+                    let _ = debugger.run_frame(&mut chip8, &mut stdout());
+                    recorder.capture(chip8.display_state());
+                }
+                continue;
+            }
+            if let Err(e) = chip8.run_frame() {
+                eprintln!("{}", e);
+                // the registers tell more than the memory, which is too big to print
+                eprintln!("PC: {:#06X}", chip8.program_counter());
+                eprint!("{:?}", chip8.registers());
+                eprintln!("stack: {:?}", chip8.stack());
+                break 'running;
+            }
+            recorder.capture(chip8.display_state());
+        }
+        // the debugger stays open to look at the final state
+        if chip8.is_halted() && debugger.is_none() {
+            break 'running;
+        }
+        frame_timer.sleep_until_next_frame();
+    }
+    if recorder.is_recording() {
+        toggle_recording(&mut recorder, options.scale, options.palette);
+    }
+}
+
+// Saves the current frame to nibble8-<unix time>.png in the working directory.
+fn save_screenshot(chip8: &Chip8, scale: usize, palette: Palette) {
+    let path = format!("nibble8-{}.png", timestamp());
+    match screenshot::save(chip8.display_state(), Path::new(&path), scale, &palette) {
+        Ok(()) => println!("Saved {}", path),
+        Err(e) => eprintln!("Unable to save {}: {}", path, e),
+    }
+}
+
+// Starts recording in the given palette, or stops and saves the recording to
+// nibble8-<unix time>.gif in the working directory.
+fn toggle_recording(recorder: &mut Recorder, scale: usize, palette: Palette) {
+    if !recorder.is_recording() {
+        *recorder = Recorder::new(palette);
+        recorder.start();
+        println!("Recording");
+        return;
+    }
+    recorder.stop();
+    let path = format!("nibble8-{}.gif", timestamp());
+    match recorder.save(Path::new(&path), scale) {
+        Ok(()) => println!("Saved {} frames to {}", recorder.frames(), path),
+        Err(e) => eprintln!("Unable to save {}: {}", path, e),
+    }
+}
+
+// Reads the debugger's commands from stdin on another thread, so the window stays responsive while
+// waiting for them.
+fn read_commands() -> Receiver<String> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        for line in stdin().lines() {
+            let Ok(line) = line else { break };
+            if sender.send(line).is_err() {
+                break;
+            }
+        }
+    });
+    receiver
+}
+
+fn timestamp() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis())
+}
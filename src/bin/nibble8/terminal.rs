@@ -0,0 +1,140 @@
+use crossterm::event::{
+    self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, KeyboardEnhancementFlags,
+    PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+};
+use crossterm::execute;
+use crossterm::terminal::{self, EnterAlternateScreen, LeaveAlternateScreen};
+use nibble8::display::terminal_display::TerminalDisplay;
+use nibble8::frame_timer::{FrameTimer, FRAME_RATE};
+use nibble8::Chip8;
+use std::io::{self, stdout};
+use std::time::Duration;
+
+use crate::Options;
+
+// Most terminals only report key presses, so without release events a key counts as held until
+// this many frames after it was last pressed (or repeated).
+const KEY_HOLD_FRAMES: u64 = 15;
+
+// Puts the terminal in raw mode on the alternate screen, and restores it when dropped.
+struct RawTerminal {
+    release_events: bool,
+}
+
+impl RawTerminal {
+    fn enter() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        execute!(stdout(), EnterAlternateScreen)?;
+        let release_events = terminal::supports_keyboard_enhancement().unwrap_or(false);
+        if release_events {
+            execute!(
+                stdout(),
+                PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::REPORT_EVENT_TYPES)
+            )?;
+        }
+        Ok(RawTerminal { release_events })
+    }
+}
+
+impl Drop for RawTerminal {
+    fn drop(&mut self) {
+        if self.release_events {
+            let _ = execute!(stdout(), PopKeyboardEnhancementFlags);
+        }
+        let _ = execute!(stdout(), LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+pub fn run(options: &Options) {
+    let raw_terminal = match RawTerminal::enter() {
+        Ok(raw_terminal) => raw_terminal,
+        Err(e) => {
+            eprintln!("Unable to set up the terminal: {}", e);
+            return;
+        }
+    };
+    if let Err(e) = run_in(&raw_terminal, options) {
+        drop(raw_terminal);
+        eprintln!("{}", e);
+    }
+}
+
+fn run_in(raw_terminal: &RawTerminal, options: &Options) -> Result<(), String> {
+    let keymap = &options.keymap;
+    let mut display = TerminalDisplay::new(stdout(), options.glyphs);
+    display.set_palette(options.palette);
+    let mut chip8 = Chip8::with_quirks(&mut display, options.quirks);
+    options.configure(&mut chip8);
+    options
+        .load_rom(&mut chip8)
+        .map_err(|e| format!("Unable to load {}: {}", options.rom, e))?;
+
+    // the frame until which each key is held, when the terminal does not report releases
+    let mut held_until = [None; 16];
+    let mut frame = 0;
+    let mut frame_timer = FrameTimer::new(FRAME_RATE);
+    'running: loop {
+        while event::poll(Duration::ZERO).map_err(|e| e.to_string())? {
+            match event::read().map_err(|e| e.to_string())? {
+                Event::Key(KeyEvent {
+                    code: KeyCode::Esc, ..
+                }) => break 'running,
+                Event::Key(KeyEvent {
+                    code: KeyCode::Char('c'),
+                    modifiers: KeyModifiers::CONTROL,
+                    ..
+                }) => break 'running,
+                Event::Key(KeyEvent { code, kind, .. }) => {
+                    let Some(key) = key_name(code).and_then(|name| keymap.get(&name)) else {
+                        continue;
+                    };
+                    if kind == KeyEventKind::Release {
+                        chip8.release_key(key);
+                    } else {
+                        chip8.press_key(key);
+                        if !raw_terminal.release_events {
+                            held_until[key as usize] = Some(frame + KEY_HOLD_FRAMES);
+                        }
+                    }
+                }
+                Event::FocusLost => chip8.clear_keys(),
+                _ => {}
+            }
+        }
+        for _ in 0..frame_timer.frames_due() {
+            if let Err(e) = chip8.run_frame() {
+                return Err(format!("{}\n{:?}", e, chip8));
+            }
+            frame += 1;
+            for (key, until) in held_until.iter_mut().enumerate() {
+                if until.is_some_and(|until| until <= frame) {
+                    *until = None;
+                    chip8.release_key(key as u8);
+                }
+            }
+        }
+        if chip8.is_halted() {
+            break 'running;
+        }
+        frame_timer.sleep_until_next_frame();
+    }
+    Ok(())
+}
+
+// The name of a key as used in keymaps, which follow SDL's key names.
+fn key_name(code: KeyCode) -> Option<String> {
+    let name = match code {
+        KeyCode::Char(' ') => "Space",
+        KeyCode::Char(c) => return Some(c.to_string()),
+        KeyCode::Up => "Up",
+        KeyCode::Down => "Down",
+        KeyCode::Left => "Left",
+        KeyCode::Right => "Right",
+        KeyCode::Enter => "Return",
+        KeyCode::Tab => "Tab",
+        KeyCode::Backspace => "Backspace",
+        _ => return None,
+    };
+    Some(name.to_string())
+}
@@ -11,13 +11,13 @@ mod test {
     #[test]
     fn it_works() {
         let byte = 0b11011100;
-        assert_eq!(get_bit_from_byte(0, &byte), true);
-        assert_eq!(get_bit_from_byte(1, &byte), true);
-        assert_eq!(get_bit_from_byte(2, &byte), false);
-        assert_eq!(get_bit_from_byte(3, &byte), true);
-        assert_eq!(get_bit_from_byte(4, &byte), true);
-        assert_eq!(get_bit_from_byte(5, &byte), true);
-        assert_eq!(get_bit_from_byte(6, &byte), false);
-        assert_eq!(get_bit_from_byte(7, &byte), false);
+        assert!(get_bit_from_byte(0, &byte));
+        assert!(get_bit_from_byte(1, &byte));
+        assert!(!get_bit_from_byte(2, &byte));
+        assert!(get_bit_from_byte(3, &byte));
+        assert!(get_bit_from_byte(4, &byte));
+        assert!(get_bit_from_byte(5, &byte));
+        assert!(!get_bit_from_byte(6, &byte));
+        assert!(!get_bit_from_byte(7, &byte));
     }
-}
\ No newline at end of file
+}
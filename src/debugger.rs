@@ -0,0 +1,578 @@
+//! A step debugger, driven by commands typed at a prompt.
+//!
+//! The frontend feeds each line the user types to [`Debugger::execute`] and calls
+//! [`Debugger::run_frame`] instead of [`Chip8::run_frame`], which does nothing while the program
+//! is paused, so the display keeps being shown while the program is inspected.
+//!
+//! ```
+//! use nibble8::debugger::Debugger;
+//! use nibble8::{Chip8, NullDisplay};
+//!
+//! let mut display = NullDisplay::new();
+//! let mut chip8 = Chip8::new(&mut display);
+//! chip8.load_rom_bytes(&[0x6A, 0x2A, 0x12, 0x02])?;
+//! let mut debugger = Debugger::new();
+//! let mut out = vec![];
+//! debugger.execute("step", &mut chip8, &mut out)?;
+//! assert_eq!(chip8.registers().read_vx(0xA), 0x2A);
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::collections::BTreeSet;
+use std::io::{self, Write};
+
+use crate::disassembler;
+use crate::machine::chip8::Chip8;
+use crate::machine::error::Chip8Error;
+use crate::machine::instruction::Instruction;
+
+const PROMPT: &str = "(nibble8) ";
+
+const HELP: &str = "\
+Commands (numbers are decimal, or hex with 0x):
+  step [n]           s   run n instructions (default 1)
+  frame [n]          f   run n frames, with the timers counting down (default 1)
+  continue           c   run until a breakpoint
+  pause              p   stop running
+  break [addr]       b   stop before running the instruction at addr, or list breakpoints
+  delete [addr]      d   remove the breakpoint at addr, or all of them
+  regs               r   show the registers, timers and stack
+  list [addr]        l   disassemble around the program counter, or from addr
+  mem [addr] [len]   x   show len bytes of memory from addr (default I)
+  set <vX|i> <value>     change a register
+  set <addr> <bytes...>  change memory
+  quit               q   exit
+An empty line repeats the last command.
+";
+
+// the most `step` and `frame` run at once, so that a mistyped count does not hang the frontend
+const MAX_STEPS: usize = 100_000;
+const MAX_FRAMES: usize = 3600;
+// how many instructions `list` shows before the program counter, and in total
+const LIST_BEFORE: usize = 3;
+const LIST_LINES: usize = 10;
+// how many bytes `mem` shows if not told, and on one line
+const MEM_LENGTH: usize = 64;
+const MEM_PER_LINE: usize = 16;
+
+/// Runs a [`Chip8`] under the control of typed commands.
+#[derive(Debug)]
+pub struct Debugger {
+    breakpoints: BTreeSet<usize>,
+    paused: bool,
+    // set when the program is resumed, so that it does not stop at the breakpoint it is paused on
+    resuming: bool,
+    last_command: String,
+}
+
+impl Default for Debugger {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Debugger {
+    /// A debugger with the program paused before its first instruction.
+    pub fn new() -> Self {
+        Debugger {
+            breakpoints: BTreeSet::new(),
+            paused: true,
+            resuming: false,
+            last_command: String::new(),
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn breakpoints(&self) -> impl Iterator<Item = usize> + '_ {
+        self.breakpoints.iter().copied()
+    }
+
+    /// Writes where the program is paused and the prompt, to start a session.
+    pub fn start(&self, chip8: &Chip8, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(out, "Paused. Type `help` for a list of commands.")?;
+        writeln!(out, "{}", self.list(chip8, chip8.program_counter(), 1))?;
+        self.prompt(out)
+    }
+
+    /// Runs one line of commands, writing what it shows to `out`. Returns false if the user
+    /// asked to quit.
+    pub fn execute(
+        &mut self,
+        line: &str,
+        chip8: &mut Chip8,
+        out: &mut dyn Write,
+    ) -> io::Result<bool> {
+        let line = match line.trim() {
+            "" => self.last_command.clone(),
+            line => line.to_string(),
+        };
+        self.last_command.clone_from(&line);
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let Some((&command, args)) = words.split_first() else {
+            self.prompt(out)?;
+            return Ok(true);
+        };
+        if matches!(command, "quit" | "q") {
+            return Ok(false);
+        }
+        match self.command(command, args, chip8) {
+            Ok(text) => write!(out, "{}", text)?,
+            Err(e) => writeln!(out, "error: {}", e)?,
+        }
+        self.prompt(out)?;
+        Ok(true)
+    }
+
+    /// Runs a frame of the program, unless it is paused, stopping early at a breakpoint or if
+    /// the program fails, in which case it is paused and what happened is written to `out`.
+    pub fn run_frame(&mut self, chip8: &mut Chip8, out: &mut dyn Write) -> io::Result<()> {
+        if self.paused {
+            return Ok(());
+        }
+        let message = match self.frame(chip8) {
+            Ok(false) => return Ok(()),
+            Ok(true) => "Breakpoint".to_string(),
+            Err(e) => format!("error: {}", e),
+        };
+        self.paused = true;
+        writeln!(out, "\n{}", message)?;
+        writeln!(out, "{}", self.list(chip8, chip8.program_counter(), 1))?;
+        self.prompt(out)
+    }
+
+    fn prompt(&self, out: &mut dyn Write) -> io::Result<()> {
+        if self.paused {
+            write!(out, "{}", PROMPT)?;
+        }
+        out.flush()
+    }
+
+    // Runs a command, returning what to show.
+    fn command(
+        &mut self,
+        command: &str,
+        args: &[&str],
+        chip8: &mut Chip8,
+    ) -> Result<String, String> {
+        let arg = |i: usize| args.get(i).map(|arg| number(arg)).transpose();
+        let size = chip8.ram().size();
+        let address_arg = |i: usize| args.get(i).map(|arg| address(arg, size)).transpose();
+        match command {
+            "help" | "h" => Ok(HELP.to_string()),
+            "step" | "s" => {
+                let count = arg(0)?.unwrap_or(1);
+                if count > MAX_STEPS {
+                    return Err(format!(
+                        "can step at most {} instructions at once",
+                        MAX_STEPS
+                    ));
+                }
+                self.paused = true;
+                let result = (0..count).try_for_each(|i| {
+                    if i > 0 && self.breakpoints.contains(&chip8.program_counter()) {
+                        return Err(None);
+                    }
+                    step(chip8).map_err(Some)
+                });
+                self.stopped(chip8, result)
+            }
+            "frame" | "f" => {
+                let count = arg(0)?.unwrap_or(1);
+                if count > MAX_FRAMES {
+                    return Err(format!("can run at most {} frames at once", MAX_FRAMES));
+                }
+                self.paused = true;
+                self.resuming = true;
+                let result = (0..count).try_for_each(|_| match self.frame(chip8) {
+                    Ok(false) => Ok(()),
+                    Ok(true) => Err(None),
+                    Err(e) => Err(Some(e)),
+                });
+                self.stopped(chip8, result)
+            }
+            "continue" | "c" => {
+                self.paused = false;
+                self.resuming = true;
+                Ok("Running. Type `pause` to stop.\n".to_string())
+            }
+            "pause" | "p" => {
+                self.paused = true;
+                Ok(format!(
+                    "{}\n",
+                    self.list(chip8, chip8.program_counter(), 1)
+                ))
+            }
+            "break" | "b" => match address_arg(0)? {
+                Some(address) => {
+                    self.breakpoints.insert(address);
+                    Ok(format!("Breakpoint at {:#05X}\n", address))
+                }
+                None if self.breakpoints.is_empty() => Ok("No breakpoints\n".to_string()),
+                None => Ok(self
+                    .breakpoints
+                    .iter()
+                    .map(|address| format!("Breakpoint at {:#05X}\n", address))
+                    .collect()),
+            },
+            "delete" | "d" => match address_arg(0)? {
+                Some(address) if self.breakpoints.remove(&address) => {
+                    Ok(format!("Deleted the breakpoint at {:#05X}\n", address))
+                }
+                Some(address) => Err(format!("there is no breakpoint at {:#05X}", address)),
+                None => {
+                    self.breakpoints.clear();
+                    Ok("Deleted all breakpoints\n".to_string())
+                }
+            },
+            "regs" | "r" => Ok(registers(chip8)),
+            "list" | "l" => {
+                let pc = chip8.program_counter();
+                let start = address_arg(0)?.unwrap_or(pc.saturating_sub(LIST_BEFORE * 2));
+                Ok(format!("{}\n", self.list(chip8, start, LIST_LINES)))
+            }
+            "mem" | "x" => {
+                let address = address_arg(0)?.unwrap_or(chip8.registers().read_i());
+                let length = arg(1)?.unwrap_or(MEM_LENGTH);
+                memory(chip8, address, length)
+            }
+            "set" => set(chip8, args),
+            _ => Err(format!(
+                "unknown command `{}`; type `help` for a list of commands",
+                command
+            )),
+        }
+    }
+
+    // Runs a frame, returning true if it stopped at a breakpoint.
+    fn frame(&mut self, chip8: &mut Chip8) -> Result<bool, Chip8Error> {
+        for _ in 0..chip8.ipf() {
+            if chip8.is_halted() {
+                break;
+            }
+            if !self.resuming && self.breakpoints.contains(&chip8.program_counter()) {
+                chip8.present();
+                return Ok(true);
+            }
+            self.resuming = false;
+            chip8.step()?;
+        }
+        self.resuming = false;
+        chip8.end_frame();
+        Ok(false)
+    }
+
+    // Describes where stepping stopped: at a breakpoint (Err(None)), on an error or as asked.
+    fn stopped(
+        &self,
+        chip8: &mut Chip8,
+        result: Result<(), Option<Chip8Error>>,
+    ) -> Result<String, String> {
+        chip8.present();
+        let line = self.list(chip8, chip8.program_counter(), 1);
+        match result {
+            Ok(()) if chip8.is_halted() => Ok(format!("Halted\n{}\n", line)),
+            Ok(()) => Ok(format!("{}\n", line)),
+            Err(None) => Ok(format!("Breakpoint\n{}\n", line)),
+            Err(Some(e)) => Err(format!("{}\n{}", e, line)),
+        }
+    }
+
+    // Disassembles `lines` instructions from `start`, marking the program counter with `>` and
+    // breakpoints with `*`.
+    fn list(&self, chip8: &Chip8, start: usize, lines: usize) -> String {
+        let size = chip8.ram().size();
+        let start = start.min(size - 1);
+        // enough bytes for every line to be a 4 byte instruction
+        let length = (lines * 4).min(size - start);
+        let bytes = chip8.ram().read_bytes(start, length).unwrap_or_default();
+        disassembler::disassemble(bytes, start)
+            .iter()
+            .take(lines)
+            .map(|line| {
+                let breakpoint = if self.breakpoints.contains(&line.address) {
+                    '*'
+                } else {
+                    ' '
+                };
+                let pc = if line.address == chip8.program_counter() {
+                    '>'
+                } else {
+                    ' '
+                };
+                format!("{}{} {}", breakpoint, pc, line)
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+// Runs one instruction. A draw that waits for the display (the VIP quirk) would only run once the
+// frame ends, which stepping never does, so the frame is ended and the draw run. The timers are
+// put back afterwards, as only `frame` lets time pass.
+fn step(chip8: &mut Chip8) -> Result<(), Chip8Error> {
+    let pc = chip8.program_counter();
+    chip8.step()?;
+    let waiting = chip8.program_counter() == pc
+        && chip8.quirks().display_wait
+        && chip8
+            .ram()
+            .read_bytes(pc, 2)
+            .is_ok_and(|bytes| matches!(Instruction::try_from(bytes), Ok(Instruction::_Dxyn(..))));
+    if waiting {
+        let registers = chip8.registers();
+        let timers = (registers.read_delay_timer(), registers.read_sound_timer());
+        chip8.end_frame();
+        let registers = chip8.registers_mut();
+        registers.set_delay_timer(timers.0);
+        registers.set_sound_timer(timers.1);
+        chip8.step()?;
+    }
+    Ok(())
+}
+
+fn registers(chip8: &Chip8) -> String {
+    let registers = chip8.registers();
+    let mut text = format!(
+        "PC {:#06X}  I {:#06X}  DT {:#04X}  ST {:#04X}\n",
+        chip8.program_counter(),
+        registers.read_i(),
+        registers.read_delay_timer(),
+        registers.read_sound_timer()
+    );
+    for row in 0..2 {
+        let values: Vec<String> = (row * 8..row * 8 + 8)
+            .map(|x| format!("V{:X} {:#04X}", x, registers.read_vx(x)))
+            .collect();
+        text.push_str(&values.join("  "));
+        text.push('\n');
+    }
+    let stack: Vec<String> = chip8
+        .stack()
+        .iter()
+        .map(|a| format!("{:#06X}", a))
+        .collect();
+    text.push_str(&format!("stack: {}\n", stack.join(" ")));
+    text
+}
+
+fn memory(chip8: &Chip8, address: usize, length: usize) -> Result<String, String> {
+    let bytes = chip8
+        .ram()
+        .read_bytes(address, length)
+        .map_err(|e| e.to_string())?;
+    Ok(bytes
+        .chunks(MEM_PER_LINE)
+        .enumerate()
+        .map(|(i, chunk)| {
+            let hex: Vec<String> = chunk.iter().map(|b| format!("{:02X}", b)).collect();
+            format!("{:#06X}  {}\n", address + i * MEM_PER_LINE, hex.join(" "))
+        })
+        .collect())
+}
+
+// `set vX value`, `set i value` or `set addr bytes...`.
+fn set(chip8: &mut Chip8, args: &[&str]) -> Result<String, String> {
+    let usage = || "expected `set <vX|i> <value>` or `set <addr> <bytes...>`".to_string();
+    let (&target, values) = args.split_first().ok_or_else(usage)?;
+    let values = values
+        .iter()
+        .map(|value| number(value))
+        .collect::<Result<Vec<usize>, String>>()?;
+    let register = target
+        .strip_prefix(['v', 'V'])
+        .filter(|digit| digit.len() == 1)
+        .and_then(|digit| u8::from_str_radix(digit, 16).ok());
+    match (register, target, values.as_slice()) {
+        (Some(x), _, &[value]) => {
+            let value =
+                u8::try_from(value).map_err(|_| format!("{} does not fit in V{:X}", value, x))?;
+            chip8.registers_mut().write_vx(x, value);
+        }
+        (None, "i" | "I", &[value]) if value > 0xFFFF => {
+            return Err(format!("{} does not fit in I", value));
+        }
+        (None, "i" | "I", &[value]) => chip8.registers_mut().write_i(value),
+        (None, _, values) if !values.is_empty() => {
+            let address = address(target, chip8.ram().size())?;
+            let bytes = values
+                .iter()
+                .map(|&value| {
+                    u8::try_from(value).map_err(|_| format!("{} does not fit in a byte", value))
+                })
+                .collect::<Result<Vec<u8>, String>>()?;
+            chip8
+                .ram_mut()
+                .write_bytes(address, &bytes)
+                .map_err(|e| e.to_string())?;
+        }
+        _ => return Err(usage()),
+    }
+    Ok(String::new())
+}
+
+fn number(text: &str) -> Result<usize, String> {
+    let parsed = match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => usize::from_str_radix(hex, 16),
+        None => text.parse(),
+    };
+    parsed.map_err(|_| format!("invalid number `{}`", text))
+}
+
+// A number that is a valid address in `size` bytes of memory.
+fn address(text: &str, size: usize) -> Result<usize, String> {
+    number(text).and_then(|address| {
+        if address < size {
+            Ok(address)
+        } else {
+            Err(format!(
+                "{:#X} is not an address, expected 0 to {:#X}",
+                address,
+                size - 1
+            ))
+        }
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::display::null_display::NullDisplay;
+    use crate::machine::quirks::Quirks;
+
+    // Runs commands, returning what they wrote without the prompts.
+    fn run(debugger: &mut Debugger, chip8: &mut Chip8, commands: &[&str]) -> String {
+        let mut out = vec![];
+        for command in commands {
+            assert!(debugger.execute(command, chip8, &mut out).unwrap());
+        }
+        String::from_utf8(out).unwrap().replace(PROMPT, "")
+    }
+
+    #[test]
+    fn step_and_inspect() {
+        let mut display = NullDisplay::new();
+        let mut chip8 = Chip8::new(&mut display);
+        chip8
+            .load_rom_bytes(&[0x6A, 0x2A, 0xA3, 0x00, 0x12, 0x04])
+            .unwrap();
+        let mut debugger = Debugger::new();
+
+        let output = run(&mut debugger, &mut chip8, &["step", ""]);
+        assert_eq!(
+            output,
+            " > 0x202  A300       LD I, 0x300\n > 0x204  1204       JP 0x204\n"
+        );
+        let output = run(&mut debugger, &mut chip8, &["regs"]);
+        assert!(output.starts_with("PC 0x0204  I 0x0300  DT 0x00  ST 0x00\n"));
+        assert!(output.contains("VA 0x2A"));
+
+        let output = run(
+            &mut debugger,
+            &mut chip8,
+            &["set 0x300 0xF0 144", "set v1 7", "x"],
+        );
+        assert!(output.starts_with("0x0300  F0 90 00"));
+        assert_eq!(chip8.registers().read_vx(1), 7);
+    }
+
+    #[test]
+    fn breakpoints() {
+        let mut display = NullDisplay::new();
+        let mut chip8 = Chip8::new(&mut display);
+        // count up in V0 forever
+        chip8
+            .load_rom_bytes(&[0x70, 0x01, 0x00, 0xE0, 0x12, 0x00])
+            .unwrap();
+        let mut debugger = Debugger::new();
+        let mut out = vec![];
+
+        let output = run(&mut debugger, &mut chip8, &["b 0x202", "c"]);
+        assert_eq!(
+            output,
+            "Breakpoint at 0x202\nRunning. Type `pause` to stop.\n"
+        );
+        assert!(!debugger.is_paused());
+        debugger.run_frame(&mut chip8, &mut out).unwrap();
+        assert!(debugger.is_paused());
+        assert_eq!(chip8.program_counter(), 0x202);
+        assert_eq!(chip8.registers().read_vx(0), 1);
+
+        // continuing runs past the breakpoint it is paused on, until it comes round again
+        run(&mut debugger, &mut chip8, &["c"]);
+        debugger.run_frame(&mut chip8, &mut out).unwrap();
+        assert_eq!(chip8.program_counter(), 0x202);
+        assert_eq!(chip8.registers().read_vx(0), 2);
+
+        let output = run(&mut debugger, &mut chip8, &["l 0x200"]);
+        assert!(output.contains("\n*> 0x202  00E0       CLS\n"));
+        let output = run(&mut debugger, &mut chip8, &["d", "b"]);
+        assert_eq!(output, "Deleted all breakpoints\nNo breakpoints\n");
+    }
+
+    #[test]
+    fn step_waits_for_display() {
+        let mut display = NullDisplay::new();
+        let mut chip8 = Chip8::with_quirks(&mut display, Quirks::COSMAC_VIP);
+        chip8
+            .load_rom_bytes(&[0xD0, 0x05, 0xD0, 0x05, 0x12, 0x04])
+            .unwrap();
+        let mut debugger = Debugger::new();
+
+        chip8.registers_mut().set_delay_timer(10);
+        chip8.registers_mut().set_sound_timer(20);
+
+        let output = run(&mut debugger, &mut chip8, &["step", "step"]);
+        assert_eq!(
+            output,
+            " > 0x202  D005       DRW V0, V0, 5\n > 0x204  1204       JP 0x204\n"
+        );
+        // stepping does not let time pass, even when it ends a frame to draw
+        assert_eq!(chip8.registers().read_delay_timer(), 10);
+        assert_eq!(chip8.registers().read_sound_timer(), 20);
+    }
+
+    #[test]
+    fn errors() {
+        let mut display = NullDisplay::new();
+        let mut chip8 = Chip8::new(&mut display);
+        chip8.load_rom_bytes(&[0xFF, 0xFF]).unwrap();
+        let mut debugger = Debugger::new();
+
+        let output = run(
+            &mut debugger,
+            &mut chip8,
+            &[
+                "jump",
+                "step",
+                "set v1 300",
+                "set i 0x10000",
+                "mem 0xFFFFFFFFFFFFFFFF",
+                "set 0xFFFFFFFFFFFFFFFF 1",
+                "mem 0xFFF 0xFFFFFFFFFFFFFFFF",
+                "mem 0x1000",
+                "step 1000000",
+                "frame 1000000",
+            ],
+        );
+        assert_eq!(
+            output,
+            "error: unknown command `jump`; type `help` for a list of commands
+error: invalid opcode 0xFFFF at 0x0200
+ > 0x200  FFFF       db 0xFF, 0xFF
+error: 300 does not fit in V1
+error: 65536 does not fit in I
+error: 0xFFFFFFFFFFFFFFFF is not an address, expected 0 to 0xFFF
+error: 0xFFFFFFFFFFFFFFFF is not an address, expected 0 to 0xFFF
+error: memory access out of bounds: 18446744073709551615 bytes at 0x0FFF
+error: 0x1000 is not an address, expected 0 to 0xFFF
+error: can step at most 100000 instructions at once
+error: can run at most 3600 frames at once
+"
+        );
+        let mut out = vec![];
+        assert!(!debugger.execute("quit", &mut chip8, &mut out).unwrap());
+    }
+}
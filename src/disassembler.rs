@@ -0,0 +1,361 @@
+//! Turns Chip-8 programs back into mnemonics.
+//!
+//! [`disassemble`] decodes a program from start to end, while [`trace`] follows its control flow
+//! to tell code from data and produces source that can be assembled again.
+//!
+//! ```
+//! use nibble8::disassembler;
+//!
+//! let lines = disassembler::disassemble(&[0x6A, 0x2A, 0xF0, 0x90], 0x200);
+//! assert_eq!(lines[0].to_string(), "0x200  6A2A       LD VA, 0x2A");
+//! assert_eq!(lines[1].to_string(), "0x202  F090       db 0xF0, 0x90");
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::io::{self, Write};
+
+use crate::machine::instruction::Instruction;
+
+/// One instruction, or a run of bytes that is not one, at an address.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Line {
+    pub address: usize,
+    pub bytes: Vec<u8>,
+    /// None if the bytes do not decode to an instruction.
+    pub instruction: Option<Instruction>,
+}
+
+/// Formats the line as `<address>  <bytes>  <mnemonic>`, with data written as a `db` directive.
+impl Display for Line {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let hex: String = self.bytes.iter().map(|b| format!("{:02X}", b)).collect();
+        write!(f, "{:#05X}  {:<9}  ", self.address, hex)?;
+        match self.instruction {
+            Some(instruction) => write!(f, "{}", instruction),
+            None => {
+                let bytes: Vec<String> = self.bytes.iter().map(|b| format!("{:#04X}", b)).collect();
+                write!(f, "db {}", bytes.join(", "))
+            }
+        }
+    }
+}
+
+/// Decodes `rom`, loaded at `origin`, from start to end.
+///
+/// Every two bytes are read as an instruction, or four for the XO-CHIP `LD I, LONG`. Words that
+/// do not decode, and a trailing odd byte, become data. Since sprites and other data are decoded
+/// too whenever they happen to look like instructions, this is best read alongside the program.
+pub fn disassemble(rom: &[u8], origin: usize) -> Vec<Line> {
+    let mut lines = vec![];
+    let mut offset = 0;
+    while offset < rom.len() {
+        let rest = &rom[offset..];
+        let instruction = if rest.len() >= 2 {
+            Instruction::try_from(rest).ok()
+        } else {
+            None
+        };
+        let size = instruction.map_or(rest.len().min(2), |i| i.size());
+        lines.push(Line {
+            address: origin + offset,
+            bytes: rest[..size].to_vec(),
+            instruction,
+        });
+        offset += size;
+    }
+    lines
+}
+
+/// Writes the disassembly of `rom`, loaded at `origin`, one line per instruction.
+pub fn write_listing(rom: &[u8], origin: usize, out: &mut dyn Write) -> io::Result<()> {
+    for line in disassemble(rom, origin) {
+        writeln!(out, "{}", line)?;
+    }
+    Ok(())
+}
+
+// how many bytes of data go on one `db` line of source
+const DATA_PER_LINE: usize = 8;
+
+/// A program split into code and data by [`trace`].
+#[derive(Debug, Clone)]
+pub struct Disassembly {
+    origin: usize,
+    rom: Vec<u8>,
+    instructions: BTreeMap<usize, Instruction>,
+    labels: BTreeMap<usize, String>,
+    unresolved: Vec<usize>,
+}
+
+impl Disassembly {
+    /// The instruction at `address`, if it was reached as code.
+    pub fn instruction(&self, address: usize) -> Option<Instruction> {
+        self.instructions.get(&address).copied()
+    }
+
+    /// The label generated for `address`, if anything jumps to, calls or points at it.
+    pub fn label(&self, address: usize) -> Option<&str> {
+        self.labels.get(&address).map(String::as_str)
+    }
+
+    /// The addresses of the computed jumps (`JP V0, addr`), whose targets could not be followed.
+    pub fn unresolved(&self) -> &[usize] {
+        &self.unresolved
+    }
+
+    /// Writes the program as assembly source, with labels in place of the addresses they name
+    /// and everything that was not reached as code written as `db` data.
+    pub fn write_source(&self, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(out, "; traced from {:#05X}", self.origin)?;
+        let mut offset = 0;
+        while offset < self.rom.len() {
+            let address = self.origin + offset;
+            if let Some(label) = self.labels.get(&address) {
+                writeln!(out, "{}:", label)?;
+            }
+            if let Some(instruction) = self.instructions.get(&address) {
+                write!(out, "    {}", self.mnemonic(*instruction))?;
+                if let Instruction::_Bnnn(_) = instruction {
+                    write!(out, " ; unresolved computed jump")?;
+                }
+                writeln!(out)?;
+                offset += instruction.size();
+                continue;
+            }
+            // data runs up to the next instruction or label
+            let end = (offset + 1..self.rom.len())
+                .find(|&end| {
+                    end - offset == DATA_PER_LINE
+                        || self.instructions.contains_key(&(self.origin + end))
+                        || self.labels.contains_key(&(self.origin + end))
+                })
+                .unwrap_or(self.rom.len());
+            let bytes: Vec<String> = self.rom[offset..end]
+                .iter()
+                .map(|b| format!("{:#04X}", b))
+                .collect();
+            writeln!(out, "    db {}", bytes.join(", "))?;
+            offset = end;
+        }
+        Ok(())
+    }
+
+    // The instruction's mnemonic, with its address replaced by a label if there is one.
+    fn mnemonic(&self, instruction: Instruction) -> String {
+        let label = |address| self.labels.get(&address);
+        match instruction {
+            Instruction::_1nnn(addr) => label(addr).map(|l| format!("JP {}", l)),
+            Instruction::_2nnn(addr) => label(addr).map(|l| format!("CALL {}", l)),
+            Instruction::_Annn(addr) => label(addr).map(|l| format!("LD I, {}", l)),
+            Instruction::_Bnnn(addr) => label(addr).map(|l| format!("JP V0, {}", l)),
+            Instruction::_F000(addr) => label(addr).map(|l| format!("LD I, LONG {}", l)),
+            _ => None,
+        }
+        .unwrap_or_else(|| instruction.to_string())
+    }
+}
+
+/// Follows the control flow of `rom`, loaded at `origin`, from its first instruction.
+///
+/// Jumps, calls, returns and skips are followed to find every reachable instruction; all other
+/// bytes are taken to be data. Jump and call targets get labels, as do the addresses loaded into
+/// `I`. The target of a computed jump depends on `V0` at run time, so it is not followed but
+/// recorded in [`Disassembly::unresolved`], and code only reachable through it shows up as data.
+pub fn trace(rom: &[u8], origin: usize) -> Disassembly {
+    let decode = |address: usize| {
+        let bytes = rom.get(address.checked_sub(origin)?..)?;
+        if bytes.len() < 2 {
+            return None;
+        }
+        Instruction::try_from(bytes).ok()
+    };
+
+    let mut instructions = BTreeMap::new();
+    // whether each byte belongs to an instruction that was already decoded
+    let mut covered = vec![false; rom.len()];
+    let mut calls = vec![];
+    let mut jumps = vec![];
+    let mut pointers = vec![];
+    let mut unresolved = vec![];
+    let mut pending = vec![origin];
+    while let Some(address) = pending.pop() {
+        if instructions.contains_key(&address) {
+            continue;
+        }
+        let Some(instruction) = decode(address) else {
+            continue;
+        };
+        let offset = address - origin;
+        let size = instruction.size();
+        // a jump into the middle of another instruction; leave that one as it is
+        if covered[offset..offset + size].iter().any(|&c| c) {
+            continue;
+        }
+        covered[offset..offset + size].fill(true);
+        instructions.insert(address, instruction);
+
+        let next = address + size;
+        match instruction {
+            Instruction::_00EE | Instruction::_00FD => {}
+            Instruction::_1nnn(target) => {
+                jumps.push(target);
+                pending.push(target);
+            }
+            Instruction::_2nnn(target) => {
+                calls.push(target);
+                pending.extend([next, target]);
+            }
+            Instruction::_Bnnn(base) => {
+                jumps.push(base);
+                unresolved.push(address);
+            }
+            Instruction::_3xkk(..)
+            | Instruction::_4xkk(..)
+            | Instruction::_5xy0(..)
+            | Instruction::_9xy0(..)
+            | Instruction::_Ex9E(_)
+            | Instruction::_ExA1(_) => {
+                // skips step over the 4 byte LD I, LONG in one go
+                let skipped = next + decode(next).map_or(2, |i| i.size());
+                pending.extend([skipped, next]);
+            }
+            Instruction::_Annn(target) | Instruction::_F000(target) => {
+                pointers.push(target);
+                pending.push(next);
+            }
+            _ => pending.push(next),
+        }
+    }
+
+    // a label can only go where a line of source starts
+    let placeable = |address: &usize| {
+        address
+            .checked_sub(origin)
+            .is_some_and(|offset| offset < rom.len())
+            && (instructions.contains_key(address) || !covered[address - origin])
+    };
+    let mut labels = BTreeMap::new();
+    let named = [("data", pointers), ("label", jumps), ("sub", calls)];
+    for (prefix, addresses) in named {
+        for address in addresses.into_iter().filter(placeable) {
+            labels.insert(address, format!("{}_{:03X}", prefix, address));
+        }
+    }
+    unresolved.sort_unstable();
+
+    Disassembly {
+        origin,
+        rom: rom.to_vec(),
+        instructions,
+        labels,
+        unresolved,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn disassemble_instructions() {
+        let lines = disassemble(&[0x00, 0xE0, 0xD0, 0x15, 0xF0, 0x00, 0x12, 0x34], 0x200);
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0].instruction, Some(Instruction::_00E0));
+        assert_eq!(lines[1].address, 0x202);
+        assert_eq!(lines[2].bytes, vec![0xF0, 0x00, 0x12, 0x34]);
+        assert_eq!(lines[2].to_string(), "0x204  F0001234   LD I, LONG 0x1234");
+    }
+
+    #[test]
+    fn disassemble_data() {
+        // 0xFFFF does not decode, F000 is cut short, and the last byte is on its own
+        let lines = disassemble(&[0xFF, 0xFF, 0x61, 0xAB, 0xF0, 0x00, 0x12], 0x200);
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0].to_string(), "0x200  FFFF       db 0xFF, 0xFF");
+        assert_eq!(lines[1].to_string(), "0x202  61AB       LD V1, 0xAB");
+        assert_eq!(lines[2].instruction, None);
+        assert_eq!(lines[3].to_string(), "0x206  12         db 0x12");
+    }
+
+    fn source(disassembly: &Disassembly) -> String {
+        let mut out = vec![];
+        disassembly.write_source(&mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn trace_separates_code_and_data() {
+        let rom = [
+            0xA2, 0x0A, // LD I, sprite
+            0x22, 0x08, // CALL draw
+            0x12, 0x04, // JP self
+            0xFF, 0xFF, // never reached
+            0xD0, 0x15, // draw: DRW V0, V1, 5
+            0x00, 0xEE, // RET (and the start of the sprite, which overlaps it)
+            0x90, 0x90, 0xF0,
+        ];
+        let disassembly = trace(&rom, 0x200);
+        assert_eq!(
+            disassembly.instruction(0x208),
+            Some(Instruction::_Dxyn(0, 1, 5))
+        );
+        assert_eq!(disassembly.instruction(0x206), None);
+        assert_eq!(disassembly.label(0x208), Some("sub_208"));
+        assert_eq!(disassembly.label(0x20C), None);
+        assert_eq!(
+            source(&disassembly),
+            "; traced from 0x200
+    LD I, data_20A
+    CALL sub_208
+label_204:
+    JP label_204
+    db 0xFF, 0xFF
+sub_208:
+    DRW V0, V1, 5
+data_20A:
+    RET
+    db 0x90, 0x90, 0xF0
+"
+        );
+    }
+
+    #[test]
+    fn trace_follows_skips() {
+        let rom = [
+            0x30, 0x00, // SE V0, 0x00
+            0xF0, 0x00, 0x02, 0x0A, // LD I, LONG 0x20A, skipped in one go
+            0x00, 0xFD, // EXIT
+            0x12, 0x00, // unreachable
+            0x01,
+        ];
+        let disassembly = trace(&rom, 0x200);
+        assert_eq!(
+            disassembly.instruction(0x202),
+            Some(Instruction::_F000(0x20A))
+        );
+        assert_eq!(disassembly.instruction(0x206), Some(Instruction::_00FD));
+        assert_eq!(disassembly.instruction(0x208), None);
+        assert!(source(&disassembly).contains("    LD I, LONG data_20A\n"));
+    }
+
+    #[test]
+    fn trace_flags_computed_jumps() {
+        let rom = [0xB2, 0x04, 0x00, 0xE0, 0x12, 0x06, 0x12, 0x04];
+        let disassembly = trace(&rom, 0x200);
+        assert_eq!(disassembly.unresolved(), &[0x200]);
+        assert_eq!(disassembly.instruction(0x204), None);
+        assert!(source(&disassembly)
+            .starts_with("; traced from 0x200\n    JP V0, label_204 ; unresolved computed jump\n"));
+    }
+
+    #[test]
+    fn listing() {
+        let mut out = vec![];
+        write_listing(&[0x00, 0xEE, 0x00], 0x600, &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "0x600  00EE       RET\n0x602  00         db 0x00\n"
+        );
+    }
+}
@@ -1,3 +1,46 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::machine::display_state::PLANES;
+
+/// A frontend that can present the Chip-8 framebuffer.
+///
+/// The machine presents at most one frame per 60 Hz frame, and only when the framebuffer changed.
 pub trait Chip8Display {
-    fn draw(&mut self, bytes: &[u8]);
+    /// Present a frame. `planes` are the packed 1bpp bitplanes as returned by
+    /// [`DisplayState::planes`](crate::machine::display_state::DisplayState::planes); a pixel's
+    /// colour has bit n set if it is on in plane n.
+    fn draw(&mut self, planes: [&[u8]; PLANES]);
+
+    /// Called instead of [`draw`](Chip8Display::draw) at the end of a frame in which the
+    /// framebuffer did not change, so the previous frame is still current.
+    fn repeat_frame(&mut self) {}
+
+    /// Called when the machine switches resolution; frames drawn afterwards are `cols` x `rows`.
+    fn resize(&mut self, _cols: usize, _rows: usize) {}
+}
+
+/// A shared display, so a frontend can keep a handle on it while a machine draws to it, e.g. to
+/// change its settings at runtime.
+impl<D: Chip8Display> Chip8Display for Rc<RefCell<D>> {
+    fn draw(&mut self, planes: [&[u8]; PLANES]) {
+        self.borrow_mut().draw(planes);
+    }
+
+    fn repeat_frame(&mut self) {
+        self.borrow_mut().repeat_frame();
+    }
+
+    fn resize(&mut self, cols: usize, rows: usize) {
+        self.borrow_mut().resize(cols, rows);
+    }
+}
+
+/// The colour (0 - 3) of the pixel at (`x`, `y`) in planes passed to [`Chip8Display::draw`],
+/// for a display that is `cols` pixels wide.
+pub fn pixel_colour(planes: [&[u8]; PLANES], cols: usize, x: usize, y: usize) -> u8 {
+    let bit = y * cols + x;
+    (0..PLANES).fold(0, |colour, plane| {
+        colour | ((planes[plane][bit / 8] >> (7 - bit % 8)) & 1) << plane
+    })
 }
@@ -1,2 +1,6 @@
 pub mod chip8_display;
+pub mod null_display;
+pub mod phosphor;
+#[cfg(feature = "sdl")]
 pub mod sdl_display;
+pub mod terminal_display;
@@ -0,0 +1,26 @@
+use crate::machine::display_state::PLANES;
+
+use super::chip8_display::Chip8Display;
+
+/// A display that shows nothing, for running headless.
+#[derive(Debug, Default)]
+pub struct NullDisplay {
+    frames: u64,
+}
+
+impl NullDisplay {
+    pub fn new() -> Self {
+        NullDisplay::default()
+    }
+
+    /// The number of frames presented so far.
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+}
+
+impl Chip8Display for NullDisplay {
+    fn draw(&mut self, _planes: [&[u8]; PLANES]) {
+        self.frames += 1;
+    }
+}
@@ -0,0 +1,185 @@
+use std::str::FromStr;
+
+use crate::palette::Palette;
+
+// how much of its brightness a pixel that turned off keeps every frame, in decay mode
+const DECAY: f32 = 0.6;
+// after how many frames a pixel that turned off is no longer drawn, in decay mode
+const DECAY_FRAMES: u8 = 6;
+
+/// How long pixels stay visible after they turn off.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Persistence {
+    /// Pixels disappear as soon as they turn off.
+    Off,
+    /// Pixels that were on in the previous frame are still drawn, which hides the flicker of
+    /// sprites that are erased and redrawn in consecutive frames.
+    Or,
+    /// Pixels fade out over a few frames, like the phosphor of a CRT.
+    Decay,
+}
+
+impl Persistence {
+    pub const PRESETS: [&'static str; 3] = ["off", "or", "decay"];
+
+    // the number of frames a pixel stays visible after turning off
+    fn frames(self) -> u8 {
+        match self {
+            Persistence::Off => 0,
+            Persistence::Or => 1,
+            Persistence::Decay => DECAY_FRAMES,
+        }
+    }
+
+    // the brightness of a pixel that turned off `age` frames ago
+    fn brightness(self, age: u8) -> f32 {
+        match self {
+            Persistence::Decay => DECAY.powi(age as i32),
+            _ => 1.0,
+        }
+    }
+}
+
+impl FromStr for Persistence {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "off" => Ok(Persistence::Off),
+            "or" => Ok(Persistence::Or),
+            "decay" => Ok(Persistence::Decay),
+            _ => Err(format!(
+                "unknown persistence '{}', expected one of: {}",
+                s,
+                Persistence::PRESETS.join(", ")
+            )),
+        }
+    }
+}
+
+/// Keeps the recent history of every pixel to draw frames with [`Persistence`].
+///
+/// Feed it every 60 Hz frame with [`update`](Phosphor::update), including frames in which the
+/// framebuffer did not change, as pixels keep fading during those.
+pub struct Phosphor {
+    persistence: Persistence,
+    // for every pixel, the colour it last had while on and the number of frames since
+    glow: Vec<(u8, u8)>,
+    fading: bool,
+}
+
+impl Phosphor {
+    pub fn new(persistence: Persistence) -> Self {
+        Phosphor {
+            persistence,
+            glow: vec![],
+            fading: false,
+        }
+    }
+
+    pub fn persistence(&self) -> Persistence {
+        self.persistence
+    }
+
+    /// Add a frame, given the colour (0 - 3) of every pixel. A frame of a different size starts
+    /// the history afresh.
+    pub fn update(&mut self, colours: &[u8]) {
+        if self.glow.len() != colours.len() {
+            self.glow = vec![(0, u8::MAX); colours.len()];
+        }
+        let frames = self.persistence.frames();
+        self.fading = false;
+        for (glow, &colour) in self.glow.iter_mut().zip(colours) {
+            if colour != 0 {
+                *glow = (colour, 0);
+            } else {
+                glow.1 = glow.1.saturating_add(1);
+                self.fading |= glow.1 <= frames;
+            }
+        }
+    }
+
+    /// Whether any pixel is still fading out, i.e. whether the next frame looks different even
+    /// if the framebuffer does not change.
+    pub fn is_fading(&self) -> bool {
+        self.fading
+    }
+
+    /// Render the last frame as RGB24 into `rgb`, which holds 3 bytes for every pixel.
+    pub fn render(&self, palette: &Palette, rgb: &mut [u8]) {
+        let background = palette.colour(0);
+        let frames = self.persistence.frames();
+        for (pixel, &(colour, age)) in rgb.chunks_mut(3).zip(&self.glow) {
+            if age > frames {
+                pixel.copy_from_slice(&background);
+                continue;
+            }
+            let brightness = self.persistence.brightness(age);
+            let foreground = palette.colour(colour);
+            for (channel, (&bg, &fg)) in pixel.iter_mut().zip(background.iter().zip(&foreground)) {
+                *channel = (bg as f32 + (fg as f32 - bg as f32) * brightness).round() as u8;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn rendered(phosphor: &Phosphor) -> Vec<[u8; 3]> {
+        let mut rgb = vec![0; phosphor.glow.len() * 3];
+        phosphor.render(&Palette::GREEN, &mut rgb);
+        rgb.chunks(3).map(|c| [c[0], c[1], c[2]]).collect()
+    }
+
+    #[test]
+    fn off() {
+        let mut phosphor = Phosphor::new(Persistence::Off);
+        phosphor.update(&[1, 2]);
+        assert_eq!(rendered(&phosphor), [[0, 0xFF, 0], [0xFF, 0xAA, 0]]);
+        phosphor.update(&[0, 2]);
+        assert_eq!(rendered(&phosphor), [[0, 0, 0], [0xFF, 0xAA, 0]]);
+        assert!(!phosphor.is_fading());
+    }
+
+    #[test]
+    fn or() {
+        let mut phosphor = Phosphor::new(Persistence::Or);
+        phosphor.update(&[1, 0]);
+        phosphor.update(&[0, 1]);
+        assert_eq!(rendered(&phosphor), [[0, 0xFF, 0], [0, 0xFF, 0]]);
+        assert!(phosphor.is_fading());
+
+        phosphor.update(&[0, 1]);
+        assert_eq!(rendered(&phosphor), [[0, 0, 0], [0, 0xFF, 0]]);
+        assert!(!phosphor.is_fading());
+    }
+
+    #[test]
+    fn decay() {
+        let mut phosphor = Phosphor::new(Persistence::Decay);
+        phosphor.update(&[1]);
+        phosphor.update(&[0]);
+        assert_eq!(rendered(&phosphor), [[0, 153, 0]]);
+        phosphor.update(&[0]);
+        assert_eq!(rendered(&phosphor), [[0, 92, 0]]);
+
+        for _ in 0..DECAY_FRAMES - 2 {
+            assert!(phosphor.is_fading());
+            phosphor.update(&[0]);
+        }
+        assert!(phosphor.is_fading());
+        phosphor.update(&[0]);
+        assert!(!phosphor.is_fading());
+        assert_eq!(rendered(&phosphor), [[0, 0, 0]]);
+    }
+
+    #[test]
+    fn resize_forgets_history() {
+        let mut phosphor = Phosphor::new(Persistence::Decay);
+        phosphor.update(&[1, 1]);
+        phosphor.update(&[0, 0, 0, 0]);
+        assert_eq!(rendered(&phosphor), [[0, 0, 0]; 4]);
+    }
+}
@@ -1,71 +1,191 @@
-use sdl2::{pixels::Color, rect::Point, render::Canvas, video::Window, Sdl};
+use sdl2::{
+    pixels::{Color, PixelFormatEnum},
+    rect::Rect,
+    render::{Canvas, Texture, TextureCreator},
+    video::{FullscreenType, Window, WindowContext},
+    Sdl,
+};
 
 use crate::{
-    bit_utils::get_bit_from_byte,
     machine::chip8::{DISPLAY_COLS, DISPLAY_ROWS},
+    machine::display_state::PLANES,
+    palette::Palette,
 };
 
-use super::chip8_display::Chip8Display;
+use super::chip8_display::{pixel_colour, Chip8Display};
+use super::phosphor::{Persistence, Phosphor};
 
+/// A display in a resizable SDL window. Frames are uploaded to a streaming texture the size of the
+/// framebuffer, which is reused until the resolution changes, and scaled up as far as the window
+/// allows while keeping the aspect ratio, with black bars filling the rest.
 pub struct SDLDisplay {
     canvas: Canvas<Window>,
+    texture_creator: TextureCreator<WindowContext>,
+    texture: Texture,
+    palette: Palette,
+    phosphor: Phosphor,
+    // the colour (0 - 3) of every pixel of the current frame
+    colours: Vec<u8>,
+    // the same frame as RGB24, uploaded to the texture
+    pixels: Vec<u8>,
+    cols: usize,
+    rows: usize,
 }
 
 impl SDLDisplay {
-    pub fn init(sdl_context: &Sdl, width: u32, height: u32) -> Self {
+    /// Open a window in which every pixel of the low-res framebuffer is `scale` x `scale`.
+    pub fn init(sdl_context: &Sdl, scale: usize, palette: Palette) -> Self {
         let video_subsystem = sdl_context.video().unwrap();
         let window = video_subsystem
-            .window("nibble8", width, height)
+            .window(
+                "nibble8",
+                (DISPLAY_COLS * scale) as u32,
+                (DISPLAY_ROWS * scale) as u32,
+            )
             .position_centered()
+            .resizable()
             .build()
             .unwrap();
 
         let mut canvas = window.into_canvas().build().unwrap();
-        canvas
-            .set_scale(
-                (width / DISPLAY_COLS as u32) as f32,
-                (height / DISPLAY_ROWS as u32) as f32,
-            )
-            .unwrap();
-        canvas.set_draw_color(Color::BLACK);
+        let [r, g, b] = palette.colour(0);
+        canvas.set_draw_color(Color::RGB(r, g, b));
         canvas.clear();
-        SDLDisplay { canvas }
+        canvas.present();
+        let texture_creator = canvas.texture_creator();
+        let texture = create_texture(&texture_creator, DISPLAY_COLS, DISPLAY_ROWS);
+        SDLDisplay {
+            canvas,
+            texture_creator,
+            texture,
+            palette,
+            phosphor: Phosphor::new(Persistence::Off),
+            colours: vec![0; DISPLAY_COLS * DISPLAY_ROWS],
+            pixels: vec![0x00; DISPLAY_COLS * DISPLAY_ROWS * 3],
+            cols: DISPLAY_COLS,
+            rows: DISPLAY_ROWS,
+        }
     }
-}
 
-impl Chip8Display for SDLDisplay {
-    fn draw(&mut self, bytes: &[u8]) {
-        let texture_creator = self.canvas.texture_creator();
-        let mut texture = texture_creator
-            .create_texture_target(
-                self.canvas.default_pixel_format(),
-                DISPLAY_COLS as u32,
-                DISPLAY_ROWS as u32,
-            )
+    pub fn palette(&self) -> Palette {
+        self.palette
+    }
+
+    /// Draw in the colours of `palette`, redrawing the current frame in them.
+    pub fn set_palette(&mut self, palette: Palette) {
+        self.palette = palette;
+        self.present();
+    }
+
+    /// Keep pixels visible for a while after they turn off, to reduce flicker.
+    pub fn set_persistence(&mut self, persistence: Persistence) {
+        self.phosphor = Phosphor::new(persistence);
+        self.phosphor.update(&self.colours);
+    }
+
+    /// Switch between a window and fullscreen.
+    pub fn toggle_fullscreen(&mut self) {
+        let window = self.canvas.window_mut();
+        let fullscreen = match window.fullscreen_state() {
+            FullscreenType::Off => FullscreenType::Desktop,
+            _ => FullscreenType::Off,
+        };
+        // not every platform can go fullscreen; the window just stays as it is
+        let _ = window.set_fullscreen(fullscreen);
+        self.redraw();
+    }
+
+    /// Draw the current frame again, e.g. after the window was resized or uncovered.
+    pub fn redraw(&mut self) {
+        self.present();
+    }
+
+    fn present(&mut self) {
+        self.phosphor.render(&self.palette, &mut self.pixels);
+        self.texture
+            .update(None, &self.pixels, self.cols * 3)
             .unwrap();
+        let (width, height) = self.canvas.output_size().unwrap();
+        let (x, y, w, h) = letterbox(width, height, self.cols as u32, self.rows as u32);
+        self.canvas.set_draw_color(Color::BLACK);
+        self.canvas.clear();
         self.canvas
-            .with_texture_canvas(&mut texture, |canvas| {
-                canvas.set_draw_color(Color::BLACK);
-                canvas.clear();
-                canvas.set_draw_color(Color::GREEN);
-                let mut row = 0;
-                let mut col = 0;
-                for byte in bytes {
-                    for bit in 0..8 {
-                        if col == DISPLAY_COLS {
-                            row += 1;
-                            col = 0;
-                        }
-                        let is_on = get_bit_from_byte(bit, byte);
-                        if is_on {
-                            canvas.draw_point(Point::new(col as i32, row)).unwrap();
-                        }
-                        col += 1;
-                    }
-                }
-            })
+            .copy(&self.texture, None, Rect::new(x, y, w, h))
             .unwrap();
-        self.canvas.copy(&texture, None, None).unwrap();
         self.canvas.present();
     }
 }
+
+// The largest area of a `width` x `height` window with the aspect ratio of a `cols` x `rows`
+// framebuffer, centred, as (x, y, width, height). Pixels are kept square and the same size by
+// scaling by a whole number whenever the window is large enough.
+fn letterbox(width: u32, height: u32, cols: u32, rows: u32) -> (i32, i32, u32, u32) {
+    let scale = (width as f32 / cols as f32).min(height as f32 / rows as f32);
+    let scale = if scale >= 1.0 { scale.floor() } else { scale };
+    let (w, h) = (
+        ((cols as f32 * scale) as u32).max(1),
+        ((rows as f32 * scale) as u32).max(1),
+    );
+    (
+        (width.saturating_sub(w) / 2) as i32,
+        (height.saturating_sub(h) / 2) as i32,
+        w,
+        h,
+    )
+}
+
+fn create_texture(creator: &TextureCreator<WindowContext>, cols: usize, rows: usize) -> Texture {
+    creator
+        .create_texture_streaming(PixelFormatEnum::RGB24, cols as u32, rows as u32)
+        .unwrap()
+}
+
+impl Chip8Display for SDLDisplay {
+    fn draw(&mut self, planes: [&[u8]; PLANES]) {
+        let cols = self.cols;
+        for (i, colour) in self.colours.iter_mut().enumerate() {
+            *colour = pixel_colour(planes, cols, i % cols, i / cols);
+        }
+        self.phosphor.update(&self.colours);
+        self.present();
+    }
+
+    fn repeat_frame(&mut self) {
+        // pixels that turned off keep fading while the framebuffer stays the same
+        if self.phosphor.is_fading() {
+            self.phosphor.update(&self.colours);
+            self.present();
+        }
+    }
+
+    fn resize(&mut self, cols: usize, rows: usize) {
+        self.cols = cols;
+        self.rows = rows;
+        self.colours = vec![0; cols * rows];
+        self.pixels = vec![0x00; cols * rows * 3];
+        let texture = create_texture(&self.texture_creator, cols, rows);
+        let old = std::mem::replace(&mut self.texture, texture);
+        // SAFETY: the texture belongs to this display's renderer, which is still alive, and is
+        // no longer used
+        unsafe { old.destroy() };
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn letterbox_scales_by_whole_numbers() {
+        assert_eq!(letterbox(640, 320, 64, 32), (0, 0, 640, 320));
+        assert_eq!(letterbox(640, 320, 128, 64), (0, 0, 640, 320));
+        assert_eq!(letterbox(700, 320, 64, 32), (30, 0, 640, 320));
+        assert_eq!(letterbox(1000, 1000, 64, 32), (20, 260, 960, 480));
+        assert_eq!(letterbox(1000, 1000, 128, 64), (52, 276, 896, 448));
+    }
+
+    #[test]
+    fn letterbox_small_window() {
+        assert_eq!(letterbox(32, 32, 64, 32), (0, 8, 32, 16));
+    }
+}
@@ -0,0 +1,277 @@
+use std::io::{self, Write};
+use std::str::FromStr;
+
+use crate::machine::chip8::{DISPLAY_COLS, DISPLAY_ROWS};
+use crate::machine::display_state::PLANES;
+use crate::palette::Palette;
+
+use super::chip8_display::{pixel_colour, Chip8Display};
+
+/// How pixels are packed into terminal characters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Glyphs {
+    /// Upper half blocks (`▀`), two pixels per character in full colour.
+    HalfBlocks,
+    /// Braille patterns, 2x4 pixels per character but only one colour per character.
+    Braille,
+}
+
+impl Glyphs {
+    pub const PRESETS: [&'static str; 2] = ["halfblocks", "braille"];
+
+    // the number of pixels across and down in one character
+    fn cell_size(self) -> (usize, usize) {
+        match self {
+            Glyphs::HalfBlocks => (1, 2),
+            Glyphs::Braille => (2, 4),
+        }
+    }
+}
+
+impl FromStr for Glyphs {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "halfblocks" => Ok(Glyphs::HalfBlocks),
+            "braille" => Ok(Glyphs::Braille),
+            _ => Err(format!(
+                "unknown glyphs '{}', expected one of: {}",
+                s,
+                Glyphs::PRESETS.join(", ")
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Cell {
+    glyph: char,
+    fg: u8,
+    bg: u8,
+}
+
+/// A display that renders to a terminal with ANSI escape codes and 24-bit colour.
+///
+/// Only characters that changed since the previous frame are redrawn, so it stays responsive over
+/// slow connections. The cursor is hidden while the display exists.
+pub struct TerminalDisplay<W: Write> {
+    out: W,
+    glyphs: Glyphs,
+    palette: Palette,
+    cols: usize,
+    rows: usize,
+    // what is currently on the terminal; empty when it has to be redrawn in full
+    cells: Vec<Cell>,
+}
+
+impl<W: Write> TerminalDisplay<W> {
+    pub fn new(out: W, glyphs: Glyphs) -> Self {
+        TerminalDisplay {
+            out,
+            glyphs,
+            palette: Palette::default(),
+            cols: DISPLAY_COLS,
+            rows: DISPLAY_ROWS,
+            cells: vec![],
+        }
+    }
+
+    /// Draw in the colours of `palette`, redrawing the current frame in them.
+    pub fn set_palette(&mut self, palette: Palette) {
+        self.palette = palette;
+        let cells = std::mem::take(&mut self.cells);
+        if self.write_cells(cells).is_err() {
+            self.cells.clear();
+        }
+    }
+
+    fn cell(&self, planes: [&[u8]; PLANES], column: usize, row: usize) -> Cell {
+        let pixel = |x, y| pixel_colour(planes, self.cols, x, y);
+        match self.glyphs {
+            Glyphs::HalfBlocks => {
+                let (top, bottom) = (pixel(column, row * 2), pixel(column, row * 2 + 1));
+                Cell {
+                    glyph: if top == bottom { ' ' } else { '▀' },
+                    fg: top,
+                    bg: bottom,
+                }
+            }
+            Glyphs::Braille => {
+                // dots are numbered down the left column, then the right, with the bottom row last
+                const DOTS: [[u32; 2]; 4] =
+                    [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+                let (mut dots, mut fg) = (0, 0);
+                for (dy, row_dots) in DOTS.iter().enumerate() {
+                    for (dx, dot) in row_dots.iter().enumerate() {
+                        let colour = pixel(column * 2 + dx, row * 4 + dy);
+                        if colour != 0 {
+                            dots |= dot;
+                            fg = fg.max(colour);
+                        }
+                    }
+                }
+                Cell {
+                    glyph: char::from_u32(0x2800 + dots).unwrap_or(' '),
+                    fg,
+                    bg: 0,
+                }
+            }
+        }
+    }
+
+    fn render(&mut self, planes: [&[u8]; PLANES]) -> io::Result<()> {
+        let (cell_cols, cell_rows) = self.glyphs.cell_size();
+        let (columns, rows) = (self.cols / cell_cols, self.rows.div_ceil(cell_rows));
+        let cells: Vec<Cell> = (0..rows)
+            .flat_map(|row| (0..columns).map(move |column| (column, row)))
+            .map(|(column, row)| self.cell(planes, column, row))
+            .collect();
+        self.write_cells(cells)
+    }
+
+    // Writes the characters that differ from those on the terminal, or all of them if the
+    // terminal has to be redrawn in full.
+    fn write_cells(&mut self, cells: Vec<Cell>) -> io::Result<()> {
+        let (cell_cols, _) = self.glyphs.cell_size();
+        let columns = self.cols / cell_cols;
+        let redraw = self.cells.len() != cells.len();
+        if redraw {
+            write!(self.out, "\x1b[?25l\x1b[0m\x1b[2J")?;
+        }
+
+        // where the cursor is, and the colours last set, to leave out escapes that change nothing
+        let mut cursor = None;
+        let mut colours = None;
+        for (i, cell) in cells.iter().enumerate() {
+            if !redraw && self.cells[i] == *cell {
+                continue;
+            }
+            let (column, row) = (i % columns, i / columns);
+            if cursor != Some((column, row)) {
+                write!(self.out, "\x1b[{};{}H", row + 1, column + 1)?;
+            }
+            if colours != Some((cell.fg, cell.bg)) {
+                let ([fr, fg, fb], [br, bg, bb]) =
+                    (self.palette.colour(cell.fg), self.palette.colour(cell.bg));
+                write!(
+                    self.out,
+                    "\x1b[38;2;{};{};{}m\x1b[48;2;{};{};{}m",
+                    fr, fg, fb, br, bg, bb
+                )?;
+                colours = Some((cell.fg, cell.bg));
+            }
+            write!(self.out, "{}", cell.glyph)?;
+            cursor = Some((column + 1, row));
+        }
+        if colours.is_some() {
+            write!(self.out, "\x1b[0m")?;
+        }
+        self.cells = cells;
+        self.out.flush()
+    }
+}
+
+impl<W: Write> Chip8Display for TerminalDisplay<W> {
+    fn draw(&mut self, planes: [&[u8]; PLANES]) {
+        // there is nowhere to report a failed write to; the next frame will try again
+        if self.render(planes).is_err() {
+            self.cells.clear();
+        }
+    }
+
+    fn resize(&mut self, cols: usize, rows: usize) {
+        self.cols = cols;
+        self.rows = rows;
+        self.cells.clear();
+    }
+}
+
+impl<W: Write> Drop for TerminalDisplay<W> {
+    fn drop(&mut self) {
+        let (_, cell_rows) = self.glyphs.cell_size();
+        let below = self.rows.div_ceil(cell_rows) + 1;
+        let _ = write!(self.out, "\x1b[0m\x1b[{};1H\x1b[?25h", below);
+        let _ = self.out.flush();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::machine::display_state::DisplayState;
+
+    fn render(display: &mut TerminalDisplay<Vec<u8>>, state: &DisplayState) -> String {
+        display.out.clear();
+        display.draw(state.planes());
+        String::from_utf8(display.out.clone()).unwrap()
+    }
+
+    #[test]
+    fn half_blocks() {
+        let mut state = DisplayState::new(64, 32);
+        state.flip(0, 0, 0);
+        state.flip(0, 1, 0);
+        state.flip(0, 1, 1);
+        let mut display = TerminalDisplay::new(vec![], Glyphs::HalfBlocks);
+        let output = render(&mut display, &state);
+
+        assert!(output.starts_with("\x1b[?25l\x1b[0m\x1b[2J\x1b[1;1H"));
+        let green = "\x1b[38;2;0;255;0m";
+        let black = "\x1b[48;2;0;0;0m";
+        assert!(output.contains(&format!("{}{}▀", green, black)));
+        assert_eq!(output.matches('▀').count(), 1);
+        assert_eq!(output.matches(' ').count(), 64 * 16 - 1);
+        assert_eq!(display.cells.len(), 64 * 16);
+    }
+
+    #[test]
+    fn redraws_only_changes() {
+        let mut state = DisplayState::new(64, 32);
+        let mut display = TerminalDisplay::new(vec![], Glyphs::HalfBlocks);
+        render(&mut display, &state);
+        assert_eq!(render(&mut display, &state), "");
+
+        state.flip(0, 10, 3);
+        state.flip(0, 11, 3);
+        let output = render(&mut display, &state);
+        // the two changed characters are next to each other, so the cursor only moves once
+        assert_eq!(
+            output,
+            "\x1b[2;11H\x1b[38;2;0;0;0m\x1b[48;2;0;255;0m▀▀\x1b[0m"
+        );
+    }
+
+    #[test]
+    fn braille() {
+        let mut state = DisplayState::new(64, 32);
+        state.flip(0, 0, 0);
+        state.flip(1, 1, 3);
+        let mut display = TerminalDisplay::new(vec![], Glyphs::Braille);
+        let output = render(&mut display, &state);
+
+        assert!(output.contains("\x1b[38;2;255;170;0m\x1b[48;2;0;0;0m\u{2881}"));
+        assert_eq!(display.cells.len(), 32 * 8);
+    }
+
+    #[test]
+    fn set_palette_redraws() {
+        let mut display = TerminalDisplay::new(vec![], Glyphs::HalfBlocks);
+        render(&mut display, &DisplayState::new(64, 32));
+        display.out.clear();
+        display.set_palette(Palette::HIGH_CONTRAST);
+        let output = String::from_utf8(display.out.clone()).unwrap();
+        assert!(output.starts_with("\x1b[?25l\x1b[0m\x1b[2J"));
+        assert_eq!(output.matches(' ').count(), 64 * 16);
+    }
+
+    #[test]
+    fn resize_redraws() {
+        let mut display = TerminalDisplay::new(vec![], Glyphs::HalfBlocks);
+        render(&mut display, &DisplayState::new(64, 32));
+        display.resize(128, 64);
+        let output = render(&mut display, &DisplayState::new(128, 64));
+        assert!(output.contains("\x1b[2J"));
+        assert_eq!(display.cells.len(), 128 * 32);
+    }
+}
@@ -0,0 +1,104 @@
+use std::time::{Duration, Instant};
+
+/// The rate at which Chip-8 timers count down and frames are presented.
+pub const FRAME_RATE: u32 = 60;
+
+// how many frames are run back to back to catch up, before giving up on the lost time
+const MAX_CATCH_UP: u32 = 4;
+
+/// Paces [`Chip8::run_frame`](crate::Chip8::run_frame) to wall-clock time.
+///
+/// Frame deadlines are computed from the start time, rather than by adding up frame durations,
+/// so rounding errors do not accumulate.
+pub struct FrameTimer {
+    frames_per_second: u32,
+    start: Instant,
+    frames: u64,
+}
+
+impl FrameTimer {
+    pub fn new(frames_per_second: u32) -> Self {
+        FrameTimer::starting_at(frames_per_second, Instant::now())
+    }
+
+    fn starting_at(frames_per_second: u32, start: Instant) -> Self {
+        FrameTimer {
+            frames_per_second,
+            start,
+            frames: 0,
+        }
+    }
+
+    fn deadline(&self, frame: u64) -> Instant {
+        self.start + Duration::from_nanos(frame * 1_000_000_000 / self.frames_per_second as u64)
+    }
+
+    /// The number of frames to run now to keep up with the wall clock. If the host has fallen
+    /// too far behind, the backlog is dropped rather than run all at once.
+    pub fn frames_due(&mut self) -> u32 {
+        self.frames_due_at(Instant::now())
+    }
+
+    fn frames_due_at(&mut self, now: Instant) -> u32 {
+        let mut due = 0;
+        while self.deadline(self.frames) <= now {
+            if due == MAX_CATCH_UP {
+                // resynchronise, counting the frames just run as having caught up to `now`
+                self.start = now;
+                self.frames = 1;
+                break;
+            }
+            self.frames += 1;
+            due += 1;
+        }
+        due
+    }
+
+    /// Sleep until the next frame is due.
+    pub fn sleep_until_next_frame(&self) {
+        let next = self.deadline(self.frames);
+        let now = Instant::now();
+        if next > now {
+            std::thread::sleep(next - now);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn frames_due() {
+        let start = Instant::now();
+        let mut timer = FrameTimer::starting_at(FRAME_RATE, start);
+
+        assert_eq!(timer.frames_due_at(start), 1);
+        assert_eq!(timer.frames_due_at(start + Duration::from_millis(10)), 0);
+        assert_eq!(timer.frames_due_at(start + Duration::from_millis(17)), 1);
+        assert_eq!(timer.frames_due_at(start + Duration::from_millis(51)), 2);
+    }
+
+    #[test]
+    fn no_drift() {
+        let start = Instant::now();
+        let mut timer = FrameTimer::starting_at(FRAME_RATE, start);
+
+        let mut frames = 0;
+        for ms in 0..=60_000 {
+            frames += timer.frames_due_at(start + Duration::from_millis(ms));
+        }
+        assert_eq!(frames, 60 * 60 + 1);
+    }
+
+    #[test]
+    fn drops_backlog() {
+        let start = Instant::now();
+        let mut timer = FrameTimer::starting_at(FRAME_RATE, start);
+
+        let late = start + Duration::from_secs(1);
+        assert_eq!(timer.frames_due_at(late), MAX_CATCH_UP);
+        assert_eq!(timer.frames_due_at(late), 0);
+        assert_eq!(timer.frames_due_at(late + Duration::from_millis(17)), 1);
+    }
+}
@@ -0,0 +1,350 @@
+//! Golden-image regression testing: run a ROM headless for a number of frames, optionally
+//! pressing keys along the way, and compare the final framebuffer against a reference image
+//! saved earlier with [`screenshot`](crate::screenshot).
+//!
+//! ```no_run
+//! use nibble8::golden::{self, Reference};
+//! use nibble8::{Chip8, NullDisplay, Palette};
+//! use std::path::Path;
+//!
+//! let mut display = NullDisplay::new();
+//! let mut chip8 = Chip8::new(&mut display);
+//! chip8.load_rom("roms/test.ch8")?;
+//! let input = golden::parse_input("10 press 5\n12 release 5")?;
+//! golden::run(&mut chip8, 120, &input)?;
+//!
+//! let reference = Reference::load(Path::new("golden/test.png"), &Palette::default())?;
+//! if let Err(e) = reference.compare(chip8.display_state(), Some(Path::new("test.diff.png"))) {
+//!     panic!("{}", e);
+//! }
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::Path;
+
+use crate::machine::chip8::Chip8;
+use crate::machine::display_state::DisplayState;
+use crate::machine::error::Chip8Error;
+use crate::palette::Palette;
+use crate::screenshot;
+
+/// Colours of the diff image: pixels that are off in both images, pixels that are the same colour
+/// in both, pixels that are on in the reference but off in the frame, and pixels that are on in
+/// the frame but not the same colour in the reference.
+pub const DIFF_PALETTE: Palette = Palette::new([
+    [0x00, 0x00, 0x00],
+    [0x40, 0x40, 0x40],
+    [0xFF, 0x00, 0x00],
+    [0x00, 0x80, 0xFF],
+]);
+
+/// A key press or release, applied before the given frame (counting from 0) runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InputEvent {
+    pub frame: u64,
+    pub key: u8,
+    pub pressed: bool,
+}
+
+/// Parse an input script: one `<frame> press <key>` or `<frame> release <key>` per line, with the
+/// key as a hex digit. Empty lines and anything after `#` are ignored.
+pub fn parse_input(script: &str) -> Result<Vec<InputEvent>, String> {
+    let mut events = vec![];
+    for (n, line) in script.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        let event = match parts[..] {
+            [frame, action @ ("press" | "release"), key] => frame
+                .parse()
+                .ok()
+                .zip(u8::from_str_radix(key, 16).ok().filter(|&k| k <= 0xF))
+                .map(|(frame, key)| InputEvent {
+                    frame,
+                    key,
+                    pressed: action == "press",
+                }),
+            _ => None,
+        };
+        match event {
+            Some(event) => events.push(event),
+            None => {
+                return Err(format!(
+                    "line {}: expected `<frame> press|release <0-F>`, got `{}`",
+                    n + 1,
+                    line
+                ))
+            }
+        }
+    }
+    Ok(events)
+}
+
+/// Press and release the keys scripted for `frame`.
+pub fn apply_input(chip8: &mut Chip8, input: &[InputEvent], frame: u64) {
+    for event in input.iter().filter(|e| e.frame == frame) {
+        if event.pressed {
+            chip8.press_key(event.key);
+        } else {
+            chip8.release_key(event.key);
+        }
+    }
+}
+
+/// Run `frames` frames, applying the scripted input, or until the machine halts.
+pub fn run(chip8: &mut Chip8, frames: u64, input: &[InputEvent]) -> Result<(), Chip8Error> {
+    for frame in 0..frames {
+        if chip8.is_halted() {
+            break;
+        }
+        apply_input(chip8, input, frame);
+        chip8.run_frame()?;
+    }
+    Ok(())
+}
+
+/// Why a frame does not match its reference image.
+#[derive(Debug)]
+pub enum GoldenError {
+    Io(io::Error),
+    SizeMismatch {
+        expected: (usize, usize),
+        actual: (usize, usize),
+    },
+    Mismatch {
+        pixels: usize,
+    },
+}
+
+impl Display for GoldenError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GoldenError::Io(e) => write!(f, "{}", e),
+            GoldenError::SizeMismatch { expected, actual } => write!(
+                f,
+                "expected a {}x{} frame, got {}x{}",
+                expected.0, expected.1, actual.0, actual.1
+            ),
+            GoldenError::Mismatch { pixels } => {
+                write!(f, "{} pixels differ from the reference", pixels)
+            }
+        }
+    }
+}
+
+impl Error for GoldenError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            GoldenError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for GoldenError {
+    fn from(e: io::Error) -> Self {
+        GoldenError::Io(e)
+    }
+}
+
+/// A reference frame, read from a PNG.
+#[derive(Debug)]
+pub struct Reference {
+    cols: usize,
+    rows: usize,
+    // the colour (0 - 3) of every pixel
+    pixels: Vec<u8>,
+}
+
+impl Reference {
+    /// Read a reference image, mapping its colours back to pixel colours through `palette`. The
+    /// image may be scaled up, as long as every pixel is a square of the same size; the scale is
+    /// worked out when comparing.
+    pub fn load(path: &Path, palette: &Palette) -> io::Result<Self> {
+        Reference::read(BufReader::new(File::open(path)?), palette)
+    }
+
+    /// Read a reference PNG from `input`.
+    pub fn read(input: impl io::Read, palette: &Palette) -> io::Result<Self> {
+        let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+        let mut decoder = png::Decoder::new(input);
+        decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+        let mut reader = decoder.read_info()?;
+        let mut data = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut data)?;
+        let channels = match info.color_type {
+            png::ColorType::Rgb => 3,
+            png::ColorType::Rgba => 4,
+            other => return Err(invalid(format!("expected an RGB image, got {:?}", other))),
+        };
+
+        let (cols, rows) = (info.width as usize, info.height as usize);
+        let mut pixels = Vec::with_capacity(cols * rows);
+        for (i, rgb) in data[..info.buffer_size()].chunks(channels).enumerate() {
+            match palette
+                .colours()
+                .iter()
+                .position(|colour| colour[..] == rgb[..3])
+            {
+                Some(colour) => pixels.push(colour as u8),
+                None => {
+                    return Err(invalid(format!(
+                        "colour {:?} at ({}, {}) is not in the palette",
+                        &rgb[..3],
+                        i % cols,
+                        i / cols
+                    )))
+                }
+            }
+        }
+        Ok(Reference { cols, rows, pixels })
+    }
+
+    /// Compare a frame against the reference. If they differ and `diff` is given, a diff image
+    /// is written there, scaled like the reference and coloured with [`DIFF_PALETTE`].
+    pub fn compare(&self, state: &DisplayState, diff: Option<&Path>) -> Result<(), GoldenError> {
+        let (cols, rows) = (state.cols(), state.rows());
+        let scale = self.cols / cols;
+        if scale == 0 || self.cols != cols * scale || self.rows != rows * scale {
+            return Err(GoldenError::SizeMismatch {
+                expected: (self.cols, self.rows),
+                actual: (cols, rows),
+            });
+        }
+
+        let expected = |x: usize, y: usize| self.pixels[y * scale * self.cols + x * scale];
+        let differ = |x: usize, y: usize| {
+            let (expected, actual) = (expected(x, y), state.pixel(x, y));
+            match (expected, actual) {
+                (e, a) if e == a => (e != 0) as u8,
+                (_, 0) => 2,
+                _ => 3,
+            }
+        };
+        let pixels = (0..rows)
+            .flat_map(|y| (0..cols).map(move |x| (x, y)))
+            .filter(|&(x, y)| differ(x, y) >= 2)
+            .count();
+        if pixels == 0 {
+            return Ok(());
+        }
+
+        if let Some(path) = diff {
+            let mut out = BufWriter::new(File::create(path)?);
+            screenshot::encode_png(cols, rows, scale, &DIFF_PALETTE, differ, &mut out)?;
+            out.flush()?;
+        }
+        Err(GoldenError::Mismatch { pixels })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::display::null_display::NullDisplay;
+    use crate::screenshot::write_png;
+
+    fn reference(state: &DisplayState, scale: usize) -> Reference {
+        let mut png = vec![];
+        write_png(state, scale, &Palette::default(), &mut png).unwrap();
+        Reference::read(png.as_slice(), &Palette::default()).unwrap()
+    }
+
+    #[test]
+    fn parse() {
+        let input = parse_input("# start\n10 press A\n\n12 release a # done\n").unwrap();
+        assert_eq!(
+            input,
+            [
+                InputEvent {
+                    frame: 10,
+                    key: 0xA,
+                    pressed: true
+                },
+                InputEvent {
+                    frame: 12,
+                    key: 0xA,
+                    pressed: false
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(
+            parse_input("1 press 1\n2 hold 1").unwrap_err(),
+            "line 2: expected `<frame> press|release <0-F>`, got `2 hold 1`"
+        );
+        assert!(parse_input("1 press 10").is_err());
+        assert!(parse_input("x press 1").is_err());
+    }
+
+    #[test]
+    fn scripted_input() {
+        // 00E0: CLS
+        // F00A: LD V0, K
+        // F029: LD F, V0
+        // D005: DRW V0, V0, 5
+        // 1208: JP 0x208
+        let rom = [0x00, 0xE0, 0xF0, 0x0A, 0xF0, 0x29, 0xD0, 0x05, 0x12, 0x08];
+        let mut display = NullDisplay::new();
+        let mut chip8 = Chip8::new(&mut display);
+        chip8.load_rom_bytes(&rom).unwrap();
+        let input = parse_input("2 press 1\n3 release 1").unwrap();
+        run(&mut chip8, 5, &input).unwrap();
+
+        // the "1" glyph, drawn at (1, 1)
+        let state = chip8.display_state();
+        assert!(state.is_on(0, 3, 1));
+        assert!(state.is_on(0, 2, 2));
+        assert!(!state.is_on(0, 1, 1));
+    }
+
+    #[test]
+    fn matches_scaled_reference() {
+        let mut state = DisplayState::new(64, 32);
+        state.flip(0, 5, 7);
+        state.flip(1, 63, 31);
+        reference(&state, 1).compare(&state, None).unwrap();
+        reference(&state, 4).compare(&state, None).unwrap();
+    }
+
+    #[test]
+    fn size_mismatch() {
+        let reference = reference(&DisplayState::new(64, 32), 1);
+        match reference.compare(&DisplayState::new(128, 64), None) {
+            Err(GoldenError::SizeMismatch { expected, actual }) => {
+                assert_eq!(expected, (64, 32));
+                assert_eq!(actual, (128, 64));
+            }
+            other => panic!("expected a size mismatch, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn writes_diff() {
+        let mut expected = DisplayState::new(64, 32);
+        expected.flip(0, 0, 0);
+        expected.flip(0, 1, 0);
+        let mut actual = DisplayState::new(64, 32);
+        actual.flip(0, 0, 0);
+        actual.flip(1, 2, 0);
+
+        let path = std::env::temp_dir().join(format!("nibble8-diff-{}.png", std::process::id()));
+        match reference(&expected, 2).compare(&actual, Some(&path)) {
+            Err(GoldenError::Mismatch { pixels }) => assert_eq!(pixels, 2),
+            other => panic!("expected a mismatch, got {:?}", other),
+        }
+        let diff = Reference::load(&path, &DIFF_PALETTE).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!((diff.cols, diff.rows), (128, 64));
+        assert_eq!(&diff.pixels[..6], &[1, 1, 2, 2, 3, 3]);
+    }
+}
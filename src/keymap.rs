@@ -0,0 +1,196 @@
+use std::collections::HashMap;
+use std::fs::read_to_string;
+use std::str::FromStr;
+
+// The layout of the COSMAC VIP hex keypad.
+const KEYPAD: [[u8; 4]; 4] = [
+    [0x1, 0x2, 0x3, 0xC],
+    [0x4, 0x5, 0x6, 0xD],
+    [0x7, 0x8, 0x9, 0xE],
+    [0xA, 0x0, 0xB, 0xF],
+];
+
+/// Maps host keys, identified by name (such as "Q", "1" or "Space"), to Chip-8 keys.
+/// Names are case insensitive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Keymap {
+    keys: HashMap<String, u8>,
+}
+
+impl Keymap {
+    /// The names accepted by `Keymap::from_str`.
+    pub const PRESETS: [&'static str; 3] = ["hex", "qwerty", "azerty"];
+
+    /// Host keys 0-9 and A-F map to the Chip-8 key with the same hex value.
+    pub fn hex() -> Keymap {
+        let keys = (0x0..=0xF)
+            .map(|key: u8| (format!("{:X}", key), key))
+            .collect();
+        Keymap { keys }
+    }
+
+    /// The 4x4 block of keys under 1234 on a QWERTY keyboard maps to the hex keypad.
+    pub fn qwerty() -> Keymap {
+        Keymap::grid([
+            ["1", "2", "3", "4"],
+            ["Q", "W", "E", "R"],
+            ["A", "S", "D", "F"],
+            ["Z", "X", "C", "V"],
+        ])
+    }
+
+    /// The 4x4 block of keys under the number row on an AZERTY keyboard maps to the hex keypad.
+    pub fn azerty() -> Keymap {
+        Keymap::grid([
+            ["&", "É", "\"", "'"],
+            ["A", "Z", "E", "R"],
+            ["Q", "S", "D", "F"],
+            ["W", "X", "C", "V"],
+        ])
+    }
+
+    fn grid(host_keys: [[&str; 4]; 4]) -> Keymap {
+        let keys = host_keys
+            .iter()
+            .flatten()
+            .zip(KEYPAD.iter().flatten())
+            .map(|(host_key, key)| (host_key.to_uppercase(), *key))
+            .collect();
+        Keymap { keys }
+    }
+
+    /// Parse a keymap config, which has one `<host key> = <chip-8 key>` mapping per line, with
+    /// the Chip-8 key in hex. Blank lines and lines starting with `#` are ignored.
+    ///
+    /// ```text
+    /// # arrow keys for movement
+    /// Up = 5
+    /// Down = 8
+    /// Space = 6
+    /// ```
+    pub fn from_config(config: &str) -> Result<Keymap, String> {
+        let mut keys = HashMap::new();
+        for (line_number, line) in config.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let parsed = line.rsplit_once('=').and_then(|(host_key, key)| {
+                let key = u8::from_str_radix(key.trim(), 16)
+                    .ok()
+                    .filter(|k| *k <= 0xF)?;
+                Some((host_key.trim().to_uppercase(), key))
+            });
+            match parsed {
+                Some((host_key, key)) if !host_key.is_empty() => {
+                    keys.insert(host_key, key);
+                }
+                _ => {
+                    return Err(format!(
+                        "line {}: expected `<host key> = <0-F>`, got `{}`",
+                        line_number + 1,
+                        line
+                    ))
+                }
+            }
+        }
+        Ok(Keymap { keys })
+    }
+
+    /// Read a keymap config from a file; see [`Keymap::from_config`].
+    pub fn load(path: &str) -> Result<Keymap, String> {
+        let config = read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        Keymap::from_config(&config).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    /// The Chip-8 key for a host key name.
+    pub fn get(&self, host_key: &str) -> Option<u8> {
+        self.keys.get(&host_key.to_uppercase()).copied()
+    }
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Keymap::qwerty()
+    }
+}
+
+impl FromStr for Keymap {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "hex" => Ok(Keymap::hex()),
+            "qwerty" => Ok(Keymap::qwerty()),
+            "azerty" => Ok(Keymap::azerty()),
+            _ => Err(format!(
+                "unknown keymap preset '{}', expected one of: {}",
+                s,
+                Keymap::PRESETS.join(", ")
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn hex() {
+        let keymap = Keymap::hex();
+        assert_eq!(keymap.get("0"), Some(0x0));
+        assert_eq!(keymap.get("9"), Some(0x9));
+        assert_eq!(keymap.get("a"), Some(0xA));
+        assert_eq!(keymap.get("F"), Some(0xF));
+        assert_eq!(keymap.get("G"), None);
+    }
+
+    #[test]
+    fn qwerty() {
+        let keymap = Keymap::qwerty();
+        assert_eq!(keymap.get("1"), Some(0x1));
+        assert_eq!(keymap.get("4"), Some(0xC));
+        assert_eq!(keymap.get("W"), Some(0x5));
+        assert_eq!(keymap.get("X"), Some(0x0));
+        assert_eq!(keymap.get("V"), Some(0xF));
+        assert_eq!(keymap.get("5"), None);
+    }
+
+    #[test]
+    fn azerty() {
+        let keymap = Keymap::azerty();
+        assert_eq!(keymap.get("&"), Some(0x1));
+        assert_eq!(keymap.get("é"), Some(0x2));
+        assert_eq!(keymap.get("A"), Some(0x4));
+        assert_eq!(keymap.get("W"), Some(0xA));
+    }
+
+    #[test]
+    fn from_config() {
+        let keymap = Keymap::from_config("# movement\nUp = 5\n\n  down=8\nSpace = a\n").unwrap();
+        assert_eq!(keymap.get("Up"), Some(0x5));
+        assert_eq!(keymap.get("Down"), Some(0x8));
+        assert_eq!(keymap.get("space"), Some(0xA));
+        assert_eq!(keymap.get("1"), None);
+    }
+
+    #[test]
+    fn from_config_invalid() {
+        assert_eq!(
+            Keymap::from_config("Up = 5\nDown = 10\n"),
+            Err("line 2: expected `<host key> = <0-F>`, got `Down = 10`".to_string())
+        );
+        assert!(Keymap::from_config("Up\n").is_err());
+        assert!(Keymap::from_config(" = 5\n").is_err());
+    }
+
+    #[test]
+    fn from_str() {
+        assert_eq!("QWERTY".parse::<Keymap>(), Ok(Keymap::qwerty()));
+        assert!("dvorak".parse::<Keymap>().is_err());
+        for preset in Keymap::PRESETS {
+            assert!(preset.parse::<Keymap>().is_ok());
+        }
+    }
+}
@@ -0,0 +1,50 @@
+//! nibble8 is a Chip-8 interpreter.
+//!
+//! The emulator core lives in [`machine`] and does not depend on any particular frontend:
+//! it renders through the [`Chip8Display`] trait and beeps through the [`Chip8Audio`] trait,
+//! so it can be driven headless or wired up to a window. An SDL2 frontend is available
+//! behind the `sdl` cargo feature (enabled by default).
+//!
+//! ```
+//! use nibble8::{Chip8, NullDisplay};
+//!
+//! let mut display = NullDisplay::new();
+//! let mut chip8 = Chip8::new(&mut display);
+//! // 6A2A: LD VA, 0x2A
+//! chip8.load_rom_bytes(&[0x6A, 0x2A])?;
+//! chip8.step()?;
+//! assert_eq!(chip8.registers().read_vx(0xA), 0x2A);
+//! # Ok::<(), nibble8::Chip8Error>(())
+//! ```
+
+pub mod assembler;
+pub mod audio;
+mod bit_utils;
+pub mod debugger;
+pub mod disassembler;
+pub mod display;
+pub mod frame_timer;
+pub mod golden;
+pub mod keymap;
+pub mod machine;
+pub mod octo;
+pub mod palette;
+pub mod recorder;
+pub mod screenshot;
+
+pub use audio::chip8_audio::Chip8Audio;
+pub use audio::null_audio::NullAudio;
+pub use display::chip8_display::Chip8Display;
+pub use display::null_display::NullDisplay;
+pub use machine::chip8::{
+    Chip8, DEFAULT_IPF, DISPLAY_COLS, DISPLAY_ROWS, HIRES_DISPLAY_COLS, HIRES_DISPLAY_ROWS,
+    PROGRAM_OFFSET,
+};
+pub use machine::display_state::{DisplayState, PLANES};
+pub use machine::error::Chip8Error;
+pub use machine::instruction::Instruction;
+pub use machine::platform::Platform;
+pub use machine::quirks::Quirks;
+pub use machine::ram::Ram;
+pub use machine::registers::Registers;
+pub use palette::Palette;
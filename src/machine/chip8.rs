@@ -1,10 +1,15 @@
+use crate::audio::chip8_audio::Chip8Audio;
+use crate::audio::pattern::DEFAULT_PITCH;
 use crate::bit_utils::get_bit_from_byte;
 use crate::display::chip8_display::Chip8Display;
 use crate::machine::display_state::DisplayState;
+use crate::machine::error::Chip8Error;
 use crate::machine::instruction::Instruction;
-use crate::machine::ram::Ram;
+use crate::machine::quirks::Quirks;
+use crate::machine::ram::{Ram, MAX_SIZE};
 use crate::machine::registers::Registers;
-use rand::Rng;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use std::fmt::{Debug, Formatter};
 use std::fs::read;
 
@@ -28,58 +33,245 @@ const FONT_SPRITES: [u8; 5 * 16] = [
     0xF0, 0x80, 0xF0, 0x80, 0x80, // F
 ];
 
-const PROGRAM_OFFSET: usize = 0x200; // offset at which the start of a program should be loaded
+// 16 SUPER-CHIP large font sprites; each sprite is 10 bytes long (8*10 pixels)
+const BIG_FONT_SPRITES: [u8; 10 * 16] = [
+    0x3C, 0x7E, 0xE7, 0xC3, 0xC3, 0xC3, 0xC3, 0xE7, 0x7E, 0x3C, // 0
+    0x18, 0x38, 0x58, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x3C, // 1
+    0x3E, 0x7F, 0xC3, 0x06, 0x0C, 0x18, 0x30, 0x60, 0xFF, 0xFF, // 2
+    0x3C, 0x7E, 0xC3, 0x03, 0x0E, 0x0E, 0x03, 0xC3, 0x7E, 0x3C, // 3
+    0x06, 0x0E, 0x1E, 0x36, 0x66, 0xC6, 0xFF, 0xFF, 0x06, 0x06, // 4
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFC, 0xFE, 0x03, 0xC3, 0x7E, 0x3C, // 5
+    0x3E, 0x7C, 0xE0, 0xC0, 0xFC, 0xFE, 0xC3, 0xC3, 0x7E, 0x3C, // 6
+    0xFF, 0xFF, 0x03, 0x06, 0x0C, 0x18, 0x30, 0x60, 0x60, 0x60, // 7
+    0x3C, 0x7E, 0xC3, 0xC3, 0x7E, 0x7E, 0xC3, 0xC3, 0x7E, 0x3C, // 8
+    0x3C, 0x7E, 0xC3, 0xC3, 0x7F, 0x3F, 0x03, 0x03, 0x3E, 0x7C, // 9
+    0x18, 0x3C, 0x66, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xC3, // A
+    0xFC, 0xFE, 0xC3, 0xC3, 0xFE, 0xFE, 0xC3, 0xC3, 0xFE, 0xFC, // B
+    0x3C, 0x7E, 0xC3, 0xC0, 0xC0, 0xC0, 0xC0, 0xC3, 0x7E, 0x3C, // C
+    0xFC, 0xFE, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFE, 0xFC, // D
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFC, 0xFC, 0xC0, 0xC0, 0xFF, 0xFF, // E
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFC, 0xFC, 0xC0, 0xC0, 0xC0, 0xC0, // F
+];
+
+const BIG_FONT_OFFSET: usize = FONT_SPRITES.len(); // the large font is stored right after the small one
+
+pub const PROGRAM_OFFSET: usize = 0x200; // offset at which the start of a program should be loaded
 
 pub const DISPLAY_COLS: usize = 64;
 pub const DISPLAY_ROWS: usize = 32;
 
+// SUPER-CHIP high resolution mode
+pub const HIRES_DISPLAY_COLS: usize = 128;
+pub const HIRES_DISPLAY_ROWS: usize = 64;
+
+const STACK_SIZE: usize = 16;
+
+/// The number of instructions executed per 60 Hz frame, unless configured otherwise.
+pub const DEFAULT_IPF: usize = 15;
+
+// Progress of an Fx0A instruction waiting for a key press.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum KeyWait {
+    Idle,
+    Waiting,
+    Pressed(u8),
+    Released(u8),
+}
+
+/// A Chip-8 machine that renders to the given [`Chip8Display`].
 pub struct Chip8<'a> {
     ram: Ram,
     program_counter: usize,
     registers: Registers,
     stack: Vec<u16>,
     display: &'a mut dyn Chip8Display,
+    audio: Option<&'a mut dyn Chip8Audio>,
     display_state: DisplayState,
-    tick: u8,
+    ipf: usize,
+    drawn_this_frame: bool,
+    quirks: Quirks,
     keys_state: [bool; 16],
-    current_key: Option<u8>,
+    key_wait: KeyWait,
+    rpl_flags: [u8; 16],
+    halted: bool,
+    audio_pattern: Option<[u8; 16]>,
+    pitch: u8,
+    rng: StdRng,
+    start_address: usize,
 }
 
 impl<'a> Chip8<'a> {
+    /// Create a machine with the font loaded into memory and all registers cleared.
     pub fn new(display: &'a mut dyn Chip8Display) -> Chip8<'a> {
-        let mut ram = Ram::initialise();
-        ram.write_bytes(0x000, &FONT_SPRITES);
+        Chip8::with_quirks(display, Quirks::default())
+    }
+
+    /// Like [`Chip8::new`], interpreting ambiguous opcodes according to `quirks`.
+    pub fn with_quirks(display: &'a mut dyn Chip8Display, quirks: Quirks) -> Chip8<'a> {
+        let mut ram = if quirks.extended_memory {
+            Ram::with_size(MAX_SIZE)
+        } else {
+            Ram::initialise()
+        };
+        // the font always fits in memory
+        ram.write_bytes(0x000, &FONT_SPRITES).unwrap();
+        ram.write_bytes(BIG_FONT_OFFSET, &BIG_FONT_SPRITES).unwrap();
         Chip8 {
             program_counter: 0x000,
             ram,
             registers: Registers::new(),
-            stack: Vec::with_capacity(16),
+            stack: Vec::with_capacity(STACK_SIZE),
             display,
+            audio: None,
             display_state: DisplayState::new(DISPLAY_COLS, DISPLAY_ROWS),
-            tick: 0,
+            ipf: DEFAULT_IPF,
+            drawn_this_frame: false,
+            quirks,
             keys_state: [false; 16],
-            current_key: None,
+            key_wait: KeyWait::Idle,
+            rpl_flags: [0x00; 16],
+            halted: false,
+            audio_pattern: None,
+            pitch: DEFAULT_PITCH,
+            rng: StdRng::from_entropy(),
+            start_address: PROGRAM_OFFSET,
+        }
+    }
+
+    /// Seed the random number generator used by `Cxkk`, so runs can be repeated exactly.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
+    /// Load ROMs at `address` instead of [`PROGRAM_OFFSET`], as some interpreters did (e.g.
+    /// 0x600 on the ETI 660). Takes effect from the next ROM loaded.
+    pub fn set_start_address(&mut self, address: usize) {
+        self.start_address = address;
+    }
+
+    /// Read a ROM from disk and load it at the start address.
+    pub fn load_rom(&mut self, file: &str) -> Result<(), Chip8Error> {
+        let bytes = read(file)?;
+        self.load_rom_bytes(&bytes)
+    }
+
+    /// Load a ROM at the start address and point the program counter at it.
+    pub fn load_rom_bytes(&mut self, bytes: &[u8]) -> Result<(), Chip8Error> {
+        let max = self.ram.size().saturating_sub(self.start_address);
+        if bytes.len() > max {
+            return Err(Chip8Error::RomTooLarge {
+                size: bytes.len(),
+                max,
+            });
+        }
+        self.ram.write_bytes(self.start_address, bytes)?;
+        self.program_counter = self.start_address;
+        Ok(())
+    }
+
+    pub fn program_counter(&self) -> usize {
+        self.program_counter
+    }
+
+    pub fn registers(&self) -> &Registers {
+        &self.registers
+    }
+
+    /// The registers, to change them from outside the program, e.g. in a debugger.
+    pub fn registers_mut(&mut self) -> &mut Registers {
+        &mut self.registers
+    }
+
+    pub fn ram(&self) -> &Ram {
+        &self.ram
+    }
+
+    /// The memory, to change it from outside the program, e.g. in a debugger.
+    pub fn ram_mut(&mut self) -> &mut Ram {
+        &mut self.ram
+    }
+
+    /// Return addresses, innermost call last.
+    pub fn stack(&self) -> &[u16] {
+        &self.stack
+    }
+
+    /// Make the machine beep through `audio` whenever the sound timer is running.
+    pub fn set_audio(&mut self, audio: &'a mut dyn Chip8Audio) {
+        self.audio = Some(audio);
+    }
+
+    /// The XO-CHIP audio pattern, if the program has loaded one.
+    pub fn audio_pattern(&self) -> Option<[u8; 16]> {
+        self.audio_pattern
+    }
+
+    /// The XO-CHIP audio pitch register.
+    pub fn pitch(&self) -> u8 {
+        self.pitch
+    }
+
+    fn update_audio_pattern(&mut self) {
+        if let (Some(pattern), Some(audio)) = (self.audio_pattern, self.audio.as_mut()) {
+            audio.set_pattern(pattern, self.pitch);
         }
     }
 
-    pub fn load_rom(&mut self, file: &str) {
-        let bytes = read(file).expect("Unable to read file");
-        self.ram.write_bytes(PROGRAM_OFFSET, &bytes);
-        self.program_counter = PROGRAM_OFFSET;
+    pub fn quirks(&self) -> Quirks {
+        self.quirks
+    }
+
+    /// The number of instructions executed per frame by [`Chip8::run_frame`].
+    pub fn ipf(&self) -> usize {
+        self.ipf
+    }
+
+    pub fn set_ipf(&mut self, ipf: usize) {
+        self.ipf = ipf;
+    }
+
+    /// Whether the program has exited (00FD); a halted machine executes no further instructions.
+    pub fn is_halted(&self) -> bool {
+        self.halted
     }
 
-    pub fn register_key(&mut self, key: u8) {
-        self.current_key = Some(key);
-        self.keys_state[key as usize] = true;
+    pub fn display_state(&self) -> &DisplayState {
+        &self.display_state
     }
 
+    /// Mark a key (0x0 - 0xF) as held down. Keys outside of that range are ignored.
+    pub fn press_key(&mut self, key: u8) {
+        let Some(state) = self.keys_state.get_mut(key as usize) else {
+            return;
+        };
+        *state = true;
+        if self.key_wait == KeyWait::Waiting {
+            self.key_wait = KeyWait::Pressed(key);
+        }
+    }
+
+    /// Mark a key (0x0 - 0xF) as no longer held down. Keys outside of that range are ignored.
+    pub fn release_key(&mut self, key: u8) {
+        let Some(state) = self.keys_state.get_mut(key as usize) else {
+            return;
+        };
+        *state = false;
+        if self.key_wait == KeyWait::Pressed(key) {
+            self.key_wait = KeyWait::Released(key);
+        }
+    }
+
+    /// Release all keys, e.g. when the frontend loses focus.
     pub fn clear_keys(&mut self) {
         self.keys_state = [false; 16];
-        self.current_key = None;
+        if let KeyWait::Pressed(_) = self.key_wait {
+            self.key_wait = KeyWait::Waiting;
+        }
     }
 
+    // Vx can hold any byte, but only 0x0 - 0xF are keys
     fn is_pressed(&self, key: u8) -> bool {
-        self.keys_state[key as usize]
+        self.keys_state.get(key as usize).copied().unwrap_or(false)
     }
 
     fn decr_timers(&mut self) {
@@ -94,74 +286,166 @@ impl<'a> Chip8<'a> {
         }
     }
 
-    fn load_sprite(&mut self, x: u8, y: u8, n_rows: u8) {
+    fn shift_source(&self, reg_x: u8, reg_y: u8) -> u8 {
+        if self.quirks.shift_uses_vy {
+            self.registers.read_vx(reg_y)
+        } else {
+            self.registers.read_vx(reg_x)
+        }
+    }
+
+    // How far Fx55/Fx65 move I on, with the load_store_increments_i quirk.
+    fn load_store_increment(&self, last_register: u8) -> usize {
+        if self.quirks.load_store_stops_short {
+            last_register as usize
+        } else {
+            last_register as usize + 1
+        }
+    }
+
+    fn set_resolution(&mut self, cols: usize, rows: usize) {
+        self.display_state.resize(cols, rows);
+        self.display.resize(cols, rows);
+    }
+
+    // Draws an 8 pixel wide sprite of `n_rows` rows, or a 16x16 sprite if `n_rows` is 0, into
+    // each selected plane. The sprite data for each plane follows that of the previous one.
+    fn load_sprite(&mut self, x: u8, y: u8, n_rows: u8) -> Result<(), Chip8Error> {
+        let cols = self.display_state.cols();
+        let rows = self.display_state.rows();
+        let (width, height) = match n_rows {
+            0 => (16, 16),
+            n => (8, n as usize),
+        };
+        let bytes_per_row = width / 8;
+
         // Chip-8 wraps around the starting coordinates for a sprite if they exceed the grid size.
-        let wrapped_x = x as usize % DISPLAY_COLS;
-        let wrapped_y = y as usize % DISPLAY_ROWS;
+        let wrapped_x = x as usize % cols;
+        let wrapped_y = y as usize % rows;
 
         self.registers.write_vx(0x0F, 0);
-        let sprite_offset = self.registers.read_i();
-
-        'bytes: for row in 0..n_rows as usize {
-            let current_y = wrapped_y + row;
-            if current_y >= DISPLAY_ROWS {
-                continue;
-            }
-            let current_byte = self
-                .ram
-                .read_bytes(sprite_offset as usize + row as usize, 1)[0];
-            for col in 0..8 {
-                let current_x = wrapped_x + col;
-                if current_x >= DISPLAY_COLS {
-                    continue 'bytes;
+        let mut sprite_offset = self.registers.read_i();
+
+        let planes: Vec<usize> = self.display_state.selected_planes().collect();
+        for plane in planes {
+            'rows: for row in 0..height {
+                let mut current_y = wrapped_y + row;
+                if current_y >= rows {
+                    if self.quirks.clipping {
+                        continue;
+                    }
+                    current_y %= rows;
                 }
-
-                let current_state = self.display_state.is_on(current_x, current_y);
-                let new_state = get_bit_from_byte(col, &current_byte);
-
-                match (current_state, new_state) {
-                    (true, true) => {
-                        self.registers.write_vx(0x0F, 1);
-                        self.display_state.flip(current_x, current_y);
+                let sprite_row = self
+                    .ram
+                    .read_bytes(sprite_offset + row * bytes_per_row, bytes_per_row)?;
+                // left-align the row in 16 bits, so that bit 15 is always the leftmost pixel
+                let pixels = match sprite_row {
+                    [byte] => (*byte as u16) << 8,
+                    [high, low] => u16::from_be_bytes([*high, *low]),
+                    _ => unreachable!(),
+                };
+                for col in 0..width {
+                    let mut current_x = wrapped_x + col;
+                    if current_x >= cols {
+                        if self.quirks.clipping {
+                            continue 'rows;
+                        }
+                        current_x %= cols;
                     }
-                    (false, true) => {
-                        self.display_state.flip(current_x, current_y);
+
+                    let current_state = self.display_state.is_on(plane, current_x, current_y);
+                    let new_state = pixels & (0x8000 >> col) != 0;
+
+                    match (current_state, new_state) {
+                        (true, true) => {
+                            self.registers.write_vx(0x0F, 1);
+                            self.display_state.flip(plane, current_x, current_y);
+                        }
+                        (false, true) => {
+                            self.display_state.flip(plane, current_x, current_y);
+                        }
+                        _ => {}
                     }
-                    _ => {}
                 }
             }
+            sprite_offset += height * bytes_per_row;
         }
+        Ok(())
     }
 
-    fn run_instruction(&mut self, instruction: Instruction) {
+    // Skips the next instruction, taking into account that F000 NNNN is twice as long as the rest.
+    fn skip_next(&mut self) {
+        match self.ram.read_bytes(self.program_counter, 2) {
+            Ok([0xF0, 0x00]) => self.program_counter += 4,
+            _ => self.program_counter += 2,
+        }
+    }
+
+    fn run_instruction(&mut self, instruction: Instruction) -> Result<(), Chip8Error> {
         self.program_counter += 0x002;
         match instruction {
+            Instruction::_00Cn(n_rows) => {
+                self.display_state.scroll_down(n_rows as usize);
+            }
             Instruction::_00E0 => {
                 self.display_state.clear();
             }
             Instruction::_00EE => {
-                self.program_counter = self.stack.pop().unwrap() as usize;
+                self.program_counter = self.stack.pop().ok_or(Chip8Error::StackUnderflow)? as usize;
+            }
+            Instruction::_00FB => {
+                self.display_state.scroll_right(4);
+            }
+            Instruction::_00FC => {
+                self.display_state.scroll_left(4);
+            }
+            Instruction::_00FD => {
+                self.halted = true;
+            }
+            Instruction::_00FE => {
+                self.set_resolution(DISPLAY_COLS, DISPLAY_ROWS);
+            }
+            Instruction::_00FF => {
+                self.set_resolution(HIRES_DISPLAY_COLS, HIRES_DISPLAY_ROWS);
             }
             Instruction::_1nnn(addr) => {
-                self.program_counter = addr as usize;
+                self.program_counter = addr;
             }
             Instruction::_2nnn(addr) => {
+                if self.stack.len() == STACK_SIZE {
+                    return Err(Chip8Error::StackOverflow);
+                }
                 self.stack.push(self.program_counter as u16);
-                self.program_counter = addr as usize;
+                self.program_counter = addr;
             }
             Instruction::_3xkk(register, value) => {
                 if self.registers.read_vx(register) == value {
-                    self.program_counter += 2;
+                    self.skip_next();
                 }
             }
             Instruction::_4xkk(register, value) => {
                 if self.registers.read_vx(register) != value {
-                    self.program_counter += 2;
+                    self.skip_next();
                 }
             }
             Instruction::_5xy0(reg_x, reg_y) => {
                 if self.registers.read_vx(reg_x) == self.registers.read_vx(reg_y) {
-                    self.program_counter += 2;
+                    self.skip_next();
+                }
+            }
+            Instruction::_5xy2(reg_x, reg_y) => {
+                let addr = self.registers.read_i();
+                for (offset, register) in register_range(reg_x, reg_y).enumerate() {
+                    self.ram
+                        .write_bytes(addr + offset, &[self.registers.read_vx(register)])?;
+                }
+            }
+            Instruction::_5xy3(reg_x, reg_y) => {
+                let addr = self.registers.read_i();
+                for (offset, register) in register_range(reg_x, reg_y).enumerate() {
+                    let bytes = self.ram.read_bytes(addr + offset, 1)?;
+                    self.registers.write_vx(register, bytes[0]);
                 }
             }
             Instruction::_6xkk(register, value) => {
@@ -180,92 +464,129 @@ impl<'a> Chip8<'a> {
                 let x = self.registers.read_vx(reg_x);
                 let y = self.registers.read_vx(reg_y);
                 self.registers.write_vx(reg_x, x | y);
+                if self.quirks.vf_reset {
+                    self.registers.write_vx(0x0F, 0);
+                }
             }
             Instruction::_8xy2(reg_x, reg_y) => {
                 let x = self.registers.read_vx(reg_x);
                 let y = self.registers.read_vx(reg_y);
                 self.registers.write_vx(reg_x, x & y);
+                if self.quirks.vf_reset {
+                    self.registers.write_vx(0x0F, 0);
+                }
             }
             Instruction::_8xy3(reg_x, reg_y) => {
                 let x = self.registers.read_vx(reg_x);
                 let y = self.registers.read_vx(reg_y);
                 self.registers.write_vx(reg_x, x ^ y);
+                if self.quirks.vf_reset {
+                    self.registers.write_vx(0x0F, 0);
+                }
             }
+            // the flag is written last, so that VF holds the flag even when it is also Vx
             Instruction::_8xy4(reg_x, reg_y) => {
-                let x = self.registers.read_vx(reg_x) as u16;
-                let y = self.registers.read_vx(reg_y) as u16;
-                let added = x + y;
-                self.registers
-                    .write_vx(0x0F, if added > u8::MAX.into() { 1 } else { 0 });
-                self.registers.write_vx(reg_x, added as u8);
+                let x = self.registers.read_vx(reg_x);
+                let y = self.registers.read_vx(reg_y);
+                let (sum, carry) = x.overflowing_add(y);
+                self.registers.write_vx(reg_x, sum);
+                self.registers.write_vx(0x0F, carry as u8);
             }
             Instruction::_8xy5(reg_x, reg_y) => {
                 let x = self.registers.read_vx(reg_x);
                 let y = self.registers.read_vx(reg_y);
-                self.registers.write_vx(0x0F, if x > y { 1 } else { 0 });
                 self.registers.write_vx(reg_x, x.wrapping_sub(y));
+                self.registers.write_vx(0x0F, if x >= y { 1 } else { 0 });
             }
-            Instruction::_8xy6(reg_x) => {
-                let x = self.registers.read_vx(reg_x);
+            Instruction::_8xy6(reg_x, reg_y) => {
+                let x = self.shift_source(reg_x, reg_y);
+                self.registers.write_vx(reg_x, x >> 1);
                 self.registers
                     .write_vx(0x0F, if get_bit_from_byte(7, &x) { 1 } else { 0 });
-                self.registers.write_vx(reg_x, x >> 1);
             }
             Instruction::_8xy7(reg_x, reg_y) => {
                 let x = self.registers.read_vx(reg_x);
                 let y = self.registers.read_vx(reg_y);
-                self.registers.write_vx(0x0F, if y > x { 1 } else { 0 });
                 self.registers.write_vx(reg_x, y.wrapping_sub(x));
+                self.registers.write_vx(0x0F, if y >= x { 1 } else { 0 });
             }
-            Instruction::_8xyE(reg_x) => {
-                let x = self.registers.read_vx(reg_x);
+            Instruction::_8xyE(reg_x, reg_y) => {
+                let x = self.shift_source(reg_x, reg_y);
+                self.registers.write_vx(reg_x, x << 1);
                 self.registers
                     .write_vx(0x0F, if get_bit_from_byte(0, &x) { 1 } else { 0 });
-                self.registers.write_vx(reg_x, x << 1);
             }
             Instruction::_9xy0(reg_x, reg_y) => {
                 let x = self.registers.read_vx(reg_x);
                 let y = self.registers.read_vx(reg_y);
                 if x != y {
-                    self.program_counter += 2;
+                    self.skip_next();
                 }
             }
             Instruction::_Annn(addr) => {
                 self.registers.write_i(addr);
             }
             Instruction::_Bnnn(addr) => {
-                let v0 = self.registers.read_vx(0x00);
-                self.program_counter = addr as usize + v0 as usize;
+                let register = if self.quirks.jump_uses_vx {
+                    (addr >> 8) as u8
+                } else {
+                    0x00
+                };
+                let offset = self.registers.read_vx(register);
+                self.program_counter = addr + offset as usize;
             }
             Instruction::_Dxyn(reg_x, reg_y, n_rows) => {
+                if self.quirks.display_wait && self.drawn_this_frame {
+                    self.program_counter -= 2;
+                    return Ok(());
+                }
+                self.drawn_this_frame = true;
                 let x = self.registers.read_vx(reg_x);
                 let y = self.registers.read_vx(reg_y);
-                self.load_sprite(x, y, n_rows);
+                self.load_sprite(x, y, n_rows)?;
             }
             Instruction::_Cxkk(register, value) => {
-                let rnd = rand::thread_rng().gen_range(0..=255) as u8;
+                let rnd = self.rng.gen_range(0..=255) as u8;
                 self.registers.write_vx(register, rnd & value);
             }
             Instruction::_Ex9E(register) => {
                 let expected_key = self.registers.read_vx(register);
                 if self.is_pressed(expected_key) {
-                    self.program_counter += 2;
+                    self.skip_next();
                 }
             }
             Instruction::_ExA1(register) => {
                 let expected_key = self.registers.read_vx(register);
                 if !self.is_pressed(expected_key) {
-                    self.program_counter += 2;
+                    self.skip_next();
                 }
             }
+            Instruction::_F000(addr) => {
+                self.program_counter += 2;
+                self.registers.write_i(addr);
+            }
+            Instruction::_Fn01(planes) => {
+                self.display_state.select_planes(planes);
+            }
+            Instruction::_F002 => {
+                let mut pattern = [0x00; 16];
+                pattern.copy_from_slice(self.ram.read_bytes(self.registers.read_i(), 16)?);
+                self.audio_pattern = Some(pattern);
+                self.update_audio_pattern();
+            }
             Instruction::_Fx07(register) => {
                 self.registers
                     .write_vx(register, self.registers.read_delay_timer());
             }
             Instruction::_Fx0A(register) => {
-                if let Some(key) = self.current_key {
+                // like the original interpreter, wait for a key to be pressed and released again
+                if let KeyWait::Released(key) = self.key_wait {
                     self.registers.write_vx(register, key);
+                    self.key_wait = KeyWait::Idle;
                 } else {
+                    if self.key_wait == KeyWait::Idle {
+                        self.key_wait = KeyWait::Waiting;
+                    }
                     self.program_counter -= 2;
                 }
             }
@@ -286,46 +607,130 @@ impl<'a> Chip8<'a> {
                 let address = byte * 5;
                 self.registers.write_i(address);
             }
+            Instruction::_Fx30(register) => {
+                let byte = self.registers.read_vx(register) as usize & 0x0F;
+                let address = BIG_FONT_OFFSET + byte * 10;
+                self.registers.write_i(address);
+            }
+            Instruction::_Fx3A(register) => {
+                self.pitch = self.registers.read_vx(register);
+                self.update_audio_pattern();
+            }
             Instruction::_Fx33(register) => {
                 let number = self.registers.read_vx(register);
                 let addr = self.registers.read_i();
-                self.ram.write_bytes(addr, &[number / 100]);
-                self.ram.write_bytes(addr + 1, &[number % 100 / 10]);
-                self.ram.write_bytes(addr + 2, &[number % 10]);
+                self.ram
+                    .write_bytes(addr, &[number / 100, number % 100 / 10, number % 10])?;
             }
             Instruction::_Fx55(last_register) => {
                 let addr = self.registers.read_i();
                 for i in 0..=last_register {
                     self.ram
-                        .write_bytes(addr + i as usize, &[self.registers.read_vx(i)]);
+                        .write_bytes(addr + i as usize, &[self.registers.read_vx(i)])?;
+                }
+                if self.quirks.load_store_increments_i {
+                    self.registers
+                        .write_i(addr + self.load_store_increment(last_register));
                 }
             }
             Instruction::_Fx65(last_register) => {
                 let addr = self.registers.read_i();
                 for i in 0..=last_register {
-                    let bytes = self.ram.read_bytes(addr + i as usize, 1);
+                    let bytes = self.ram.read_bytes(addr + i as usize, 1)?;
                     self.registers.write_vx(i, bytes[0]);
                 }
+                if self.quirks.load_store_increments_i {
+                    self.registers
+                        .write_i(addr + self.load_store_increment(last_register));
+                }
+            }
+            Instruction::_Fx75(last_register) => {
+                for i in 0..=last_register {
+                    self.rpl_flags[i as usize] = self.registers.read_vx(i);
+                }
+            }
+            Instruction::_Fx85(last_register) => {
+                for i in 0..=last_register {
+                    self.registers.write_vx(i, self.rpl_flags[i as usize]);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Execute a single instruction, without touching the timers or presenting a frame.
+    ///
+    /// On error the program counter is left pointing at the offending instruction.
+    pub fn step(&mut self) -> Result<(), Chip8Error> {
+        if self.halted {
+            return Ok(());
+        }
+        let addr = self.program_counter;
+        // most instructions are 2 bytes, but F000 NNNN needs the next word too
+        let bytes = self
+            .ram
+            .read_bytes(addr, 4)
+            .or_else(|_| self.ram.read_bytes(addr, 2))?;
+        let next_instruction = Instruction::try_from(bytes)
+            .map_err(|opcode| Chip8Error::InvalidOpcode { addr, opcode })?;
+        if let Err(e) = self.run_instruction(next_instruction) {
+            self.program_counter = addr;
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Run one 60 Hz frame: execute up to [`Chip8::ipf`] instructions, decrement the timers,
+    /// present the frame and update the audio. Call this 60 times per second.
+    pub fn run_frame(&mut self) -> Result<(), Chip8Error> {
+        for _ in 0..self.ipf {
+            if self.halted {
+                break;
             }
+            self.step()?;
         }
+        self.end_frame();
+        Ok(())
     }
 
-    pub fn tick(&mut self) {
-        self.tick += 1;
-        if self.tick % 15 == 0 {
-            self.decr_timers();
-            self.tick = 0;
+    /// Finish a 60 Hz frame without running any more instructions: decrement the timers, present
+    /// the frame and update the audio. [`Chip8::run_frame`] does this after its instructions.
+    pub fn end_frame(&mut self) {
+        self.decr_timers();
+        self.drawn_this_frame = false;
+        if self.display_state.is_dirty() {
+            self.present();
+        } else {
+            self.display.repeat_frame();
+        }
+        if let Some(audio) = self.audio.as_mut() {
+            audio.set_beeping(self.registers.read_sound_timer() > 0);
+        }
+    }
+
+    /// Draw the display if it changed since it was last drawn, e.g. after stepping through
+    /// instructions one at a time.
+    pub fn present(&mut self) {
+        if self.display_state.is_dirty() {
+            self.display.draw(self.display_state.planes());
+            self.display_state.mark_clean();
         }
-        let next_instruction: Instruction = self.ram.read_bytes(self.program_counter, 2).into();
-        self.run_instruction(next_instruction);
-        self.display.draw(self.display_state.as_bytes());
+    }
+}
+
+// Vx to Vy inclusive, counting down if y < x.
+fn register_range(x: u8, y: u8) -> Box<dyn Iterator<Item = u8>> {
+    if x <= y {
+        Box::new(x..=y)
+    } else {
+        Box::new((y..=x).rev())
     }
 }
 
 impl Debug for Chip8<'_> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{:?}", self.registers).unwrap();
-        write!(f, "{:?}", self.stack).unwrap();
+        write!(f, "{:?}", self.registers)?;
+        write!(f, "{:?}", self.stack)?;
         write!(f, "{:?}", self.ram)
     }
 }
@@ -333,10 +738,145 @@ impl Debug for Chip8<'_> {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::audio::null_audio::NullAudio;
+    use crate::machine::display_state::PLANES;
+    use crate::machine::ram::CHIP8_SIZE;
 
     struct DisplayMock {}
     impl Chip8Display for DisplayMock {
-        fn draw(&mut self, _bytes: &[u8]) {}
+        fn draw(&mut self, _planes: [&[u8]; PLANES]) {}
+    }
+
+    // records, for every frame, whether it was drawn or repeated
+    struct FramesMock {
+        drawn: Vec<bool>,
+    }
+    impl Chip8Display for FramesMock {
+        fn draw(&mut self, _planes: [&[u8]; PLANES]) {
+            self.drawn.push(true);
+        }
+
+        fn repeat_frame(&mut self) {
+            self.drawn.push(false);
+        }
+    }
+
+    struct AudioMock {
+        beeping: Vec<bool>,
+    }
+    impl Chip8Audio for AudioMock {
+        fn set_beeping(&mut self, beeping: bool) {
+            self.beeping.push(beeping);
+        }
+    }
+
+    #[test]
+    fn load_rom_bytes() {
+        let mut display = DisplayMock {};
+        let mut chip8 = Chip8::new(&mut display);
+
+        chip8.load_rom_bytes(&[0x6A, 0x2A]).unwrap();
+        assert_eq!(chip8.program_counter(), PROGRAM_OFFSET);
+        assert_eq!(
+            chip8.ram().read_bytes(PROGRAM_OFFSET, 2).unwrap(),
+            &[0x6A, 0x2A]
+        );
+
+        chip8.step().unwrap();
+        assert_eq!(chip8.program_counter(), PROGRAM_OFFSET + 2);
+        assert_eq!(chip8.registers().read_vx(0x0A), 0x2A);
+    }
+
+    #[test]
+    fn load_rom_at_start_address() {
+        let mut display = DisplayMock {};
+        let mut chip8 = Chip8::new(&mut display);
+        chip8.set_start_address(0x600);
+
+        chip8.load_rom_bytes(&[0x6A, 0x2A]).unwrap();
+        assert_eq!(chip8.program_counter(), 0x600);
+        assert_eq!(chip8.ram().read_bytes(0x600, 2).unwrap(), &[0x6A, 0x2A]);
+        assert_eq!(chip8.ram().read_bytes(PROGRAM_OFFSET, 2).unwrap(), &[0, 0]);
+
+        let rom = [0x00; CHIP8_SIZE - 0x600 + 1];
+        assert!(matches!(
+            chip8.load_rom_bytes(&rom),
+            Err(Chip8Error::RomTooLarge { max, .. }) if max == CHIP8_SIZE - 0x600
+        ));
+    }
+
+    #[test]
+    fn load_rom_too_large() {
+        let mut display = DisplayMock {};
+        let mut chip8 = Chip8::new(&mut display);
+
+        let rom = [0x00; CHIP8_SIZE - PROGRAM_OFFSET + 1];
+        assert!(matches!(
+            chip8.load_rom_bytes(&rom),
+            Err(Chip8Error::RomTooLarge { max, .. }) if max == CHIP8_SIZE - PROGRAM_OFFSET
+        ));
+    }
+
+    #[test]
+    fn load_rom_extended_memory() {
+        let mut display = DisplayMock {};
+        let mut chip8 = Chip8::with_quirks(&mut display, Quirks::MODERN);
+
+        let rom = [0x00; CHIP8_SIZE];
+        chip8.load_rom_bytes(&rom).unwrap();
+        let rom = [0x00; MAX_SIZE - PROGRAM_OFFSET + 1];
+        assert!(matches!(
+            chip8.load_rom_bytes(&rom),
+            Err(Chip8Error::RomTooLarge { .. })
+        ));
+    }
+
+    #[test]
+    fn step_invalid_opcode() {
+        let mut display = DisplayMock {};
+        let mut chip8 = Chip8::new(&mut display);
+
+        chip8.load_rom_bytes(&[0x6A, 0x2A, 0xFF, 0xFF]).unwrap();
+        chip8.step().unwrap();
+        assert!(matches!(
+            chip8.step(),
+            Err(Chip8Error::InvalidOpcode {
+                addr: 0x202,
+                opcode: 0xFFFF
+            })
+        ));
+        assert_eq!(chip8.program_counter(), 0x202);
+    }
+
+    // Loads a program written in assembly.
+    fn load_assembly(chip8: &mut Chip8, source: &str) {
+        let rom = crate::assembler::assemble(source, PROGRAM_OFFSET).unwrap();
+        chip8.load_rom_bytes(&rom).unwrap();
+    }
+
+    #[test]
+    fn call_and_return() {
+        let mut display = DisplayMock {};
+        let mut chip8 = Chip8::new(&mut display);
+        load_assembly(
+            &mut chip8,
+            "
+                CALL double
+                LD V1, V0
+            halt:
+                JP halt
+            double:
+                LD V0, 21
+                ADD V0, V0
+                RET
+            ",
+        );
+
+        for _ in 0..5 {
+            chip8.step().unwrap();
+        }
+        assert_eq!(chip8.registers().read_vx(0x01), 42);
+        assert_eq!(chip8.program_counter(), 0x204);
     }
 
     #[test]
@@ -345,21 +885,83 @@ mod test {
         let mut chip8 = Chip8::new(&mut display);
 
         // load first letter from the font
-        chip8.load_sprite(0, 0, 5);
+        chip8.load_sprite(0, 0, 5).unwrap();
         assert_ne!(
-            chip8.display_state.as_bytes(),
+            chip8.display_state.plane(0),
             &[0; DISPLAY_COLS * DISPLAY_ROWS / 8]
         );
 
         let instruction = Instruction::_00E0;
-        chip8.run_instruction(instruction);
+        chip8.run_instruction(instruction).unwrap();
 
         assert_eq!(
-            chip8.display_state.as_bytes(),
+            chip8.display_state.plane(0),
             &[0; DISPLAY_COLS * DISPLAY_ROWS / 8]
         );
     }
 
+    #[test]
+    fn scroll_down() {
+        let mut display = DisplayMock {};
+        let mut chip8 = Chip8::new(&mut display);
+
+        chip8.load_sprite(0, 0, 5).unwrap();
+        let instruction = Instruction::_00Cn(2);
+        chip8.run_instruction(instruction).unwrap();
+
+        assert!(!chip8.display_state.is_on(0, 0, 0));
+        assert!(chip8.display_state.is_on(0, 0, 2));
+    }
+
+    #[test]
+    fn scroll_right_and_left() {
+        let mut display = DisplayMock {};
+        let mut chip8 = Chip8::new(&mut display);
+
+        chip8.load_sprite(0, 0, 5).unwrap();
+        let instruction = Instruction::_00FB;
+        chip8.run_instruction(instruction).unwrap();
+
+        assert!(!chip8.display_state.is_on(0, 0, 0));
+        assert!(chip8.display_state.is_on(0, 4, 0));
+
+        let instruction = Instruction::_00FC;
+        chip8.run_instruction(instruction).unwrap();
+
+        assert!(chip8.display_state.is_on(0, 0, 0));
+        assert!(!chip8.display_state.is_on(0, 4, 0));
+    }
+
+    #[test]
+    fn switch_resolution() {
+        let mut display = DisplayMock {};
+        let mut chip8 = Chip8::new(&mut display);
+
+        let instruction = Instruction::_00FF;
+        chip8.run_instruction(instruction).unwrap();
+        assert_eq!(chip8.display_state.cols(), HIRES_DISPLAY_COLS);
+        assert_eq!(chip8.display_state.rows(), HIRES_DISPLAY_ROWS);
+
+        let instruction = Instruction::_00FE;
+        chip8.run_instruction(instruction).unwrap();
+        assert_eq!(chip8.display_state.cols(), DISPLAY_COLS);
+        assert_eq!(chip8.display_state.rows(), DISPLAY_ROWS);
+    }
+
+    #[test]
+    fn exit() {
+        let mut display = DisplayMock {};
+        let mut chip8 = Chip8::new(&mut display);
+
+        chip8.load_rom_bytes(&[0x00, 0xFD, 0x6A, 0x2A]).unwrap();
+        chip8.step().unwrap();
+        assert!(chip8.is_halted());
+
+        chip8.step().unwrap();
+        assert_eq!(chip8.program_counter, PROGRAM_OFFSET + 2);
+        assert_eq!(chip8.registers.read_vx(0x0A), 0x00);
+    }
+
     #[test]
     fn ret() {
         let mut display = DisplayMock {};
@@ -368,18 +970,30 @@ mod test {
         chip8.stack.push(0x1234);
 
         let instruction = Instruction::_00EE;
-        chip8.run_instruction(instruction);
+        chip8.run_instruction(instruction).unwrap();
 
         assert_eq!(chip8.program_counter, 0x1234);
     }
 
+    #[test]
+    fn ret_empty_stack() {
+        let mut display = DisplayMock {};
+        let mut chip8 = Chip8::new(&mut display);
+
+        let instruction = Instruction::_00EE;
+        assert!(matches!(
+            chip8.run_instruction(instruction),
+            Err(Chip8Error::StackUnderflow)
+        ));
+    }
+
     #[test]
     fn jump() {
         let mut display = DisplayMock {};
         let mut chip8 = Chip8::new(&mut display);
 
         let instruction = Instruction::_1nnn(0x1234);
-        chip8.run_instruction(instruction);
+        chip8.run_instruction(instruction).unwrap();
 
         assert_eq!(chip8.program_counter, 0x1234);
     }
@@ -392,12 +1006,29 @@ mod test {
         chip8.program_counter = 0x1234;
 
         let instruction = Instruction::_2nnn(0xAABB);
-        chip8.run_instruction(instruction);
+        chip8.run_instruction(instruction).unwrap();
 
         assert_eq!(chip8.program_counter, 0xAABB);
         assert_eq!(chip8.stack.pop(), Some(0x1236));
     }
 
+    #[test]
+    fn call_full_stack() {
+        let mut display = DisplayMock {};
+        let mut chip8 = Chip8::new(&mut display);
+
+        for _ in 0..STACK_SIZE {
+            let instruction = Instruction::_2nnn(0x200);
+            chip8.run_instruction(instruction).unwrap();
+        }
+
+        let instruction = Instruction::_2nnn(0x200);
+        assert!(matches!(
+            chip8.run_instruction(instruction),
+            Err(Chip8Error::StackOverflow)
+        ));
+    }
+
     #[test]
     fn skip_eq_skips() {
         let mut display = DisplayMock {};
@@ -406,7 +1037,7 @@ mod test {
         chip8.registers.write_vx(0x01, 0xAB);
 
         let instruction = Instruction::_3xkk(0x01, 0xAB);
-        chip8.run_instruction(instruction);
+        chip8.run_instruction(instruction).unwrap();
 
         assert_eq!(chip8.program_counter, 0x04);
     }
@@ -419,7 +1050,7 @@ mod test {
         chip8.registers.write_vx(0x01, 0xAB);
 
         let instruction = Instruction::_3xkk(0x01, 0xAC);
-        chip8.run_instruction(instruction);
+        chip8.run_instruction(instruction).unwrap();
 
         assert_eq!(chip8.program_counter, 0x02);
     }
@@ -432,7 +1063,7 @@ mod test {
         chip8.registers.write_vx(0x01, 0xAB);
 
         let instruction = Instruction::_4xkk(0x01, 0xAC);
-        chip8.run_instruction(instruction);
+        chip8.run_instruction(instruction).unwrap();
 
         assert_eq!(chip8.program_counter, 0x04);
     }
@@ -445,7 +1076,7 @@ mod test {
         chip8.registers.write_vx(0x01, 0xAB);
 
         let instruction = Instruction::_4xkk(0x01, 0xAB);
-        chip8.run_instruction(instruction);
+        chip8.run_instruction(instruction).unwrap();
 
         assert_eq!(chip8.program_counter, 0x02);
     }
@@ -459,7 +1090,7 @@ mod test {
         chip8.registers.write_vx(0x02, 0xAB);
 
         let instruction = Instruction::_5xy0(0x01, 0x02);
-        chip8.run_instruction(instruction);
+        chip8.run_instruction(instruction).unwrap();
 
         assert_eq!(chip8.program_counter, 0x04);
     }
@@ -473,20 +1104,79 @@ mod test {
         chip8.registers.write_vx(0x02, 0xAC);
 
         let instruction = Instruction::_5xy0(0x01, 0x02);
-        chip8.run_instruction(instruction);
+        chip8.run_instruction(instruction).unwrap();
 
         assert_eq!(chip8.program_counter, 0x02);
     }
 
+    #[test]
+    fn skip_long_load() {
+        let mut display = DisplayMock {};
+        let mut chip8 = Chip8::new(&mut display);
+
+        chip8
+            .load_rom_bytes(&[0x30, 0x00, 0xF0, 0x00, 0x12, 0x34, 0x6A, 0x2A])
+            .unwrap();
+        chip8.step().unwrap();
+        assert_eq!(chip8.program_counter, PROGRAM_OFFSET + 6);
+    }
+
+    #[test]
+    fn save_register_range() {
+        let mut display = DisplayMock {};
+        let mut chip8 = Chip8::new(&mut display);
+
+        chip8.registers.write_i(PROGRAM_OFFSET);
+        chip8.registers.write_vx(0x02, 0x0A);
+        chip8.registers.write_vx(0x03, 0x0F);
+        chip8.registers.write_vx(0x04, 0x01);
+
+        let instruction = Instruction::_5xy2(0x02, 0x04);
+        chip8.run_instruction(instruction).unwrap();
+        assert_eq!(
+            chip8.ram.read_bytes(PROGRAM_OFFSET, 4).unwrap(),
+            &[0x0A, 0x0F, 0x01, 0x00]
+        );
+
+        let instruction = Instruction::_5xy2(0x04, 0x02);
+        chip8.run_instruction(instruction).unwrap();
+        assert_eq!(
+            chip8.ram.read_bytes(PROGRAM_OFFSET, 4).unwrap(),
+            &[0x01, 0x0F, 0x0A, 0x00]
+        );
+        assert_eq!(chip8.registers.read_i(), PROGRAM_OFFSET);
+    }
+
+    #[test]
+    fn load_register_range() {
+        let mut display = DisplayMock {};
+        let mut chip8 = Chip8::new(&mut display);
+
+        chip8.registers.write_i(PROGRAM_OFFSET);
+        chip8
+            .ram
+            .write_bytes(PROGRAM_OFFSET, &[0x04, 0x0F, 0x0A])
+            .unwrap();
+
+        let instruction = Instruction::_5xy3(0x03, 0x01);
+        chip8.run_instruction(instruction).unwrap();
+
+        assert_eq!(chip8.registers.read_vx(0x00), 0x00);
+        assert_eq!(chip8.registers.read_vx(0x03), 0x04);
+        assert_eq!(chip8.registers.read_vx(0x02), 0x0F);
+        assert_eq!(chip8.registers.read_vx(0x01), 0x0A);
+        assert_eq!(chip8.registers.read_i(), PROGRAM_OFFSET);
+    }
+
     #[test]
     fn write_register() {
         let mut display = DisplayMock {};
         let mut chip8 = Chip8::new(&mut display);
 
         let instruction = Instruction::_6xkk(0x01, 0xAB);
-        chip8.run_instruction(instruction);
+        chip8.run_instruction(instruction).unwrap();
         let instruction = Instruction::_6xkk(0x02, 0xAC);
-        chip8.run_instruction(instruction);
+        chip8.run_instruction(instruction).unwrap();
 
         assert_eq!(chip8.registers.read_vx(0x01), 0xAB);
         assert_eq!(chip8.registers.read_vx(0x02), 0xAC);
@@ -500,7 +1190,7 @@ mod test {
         chip8.registers.write_vx(0x01, 100);
 
         let instruction = Instruction::_7xkk(0x01, 114);
-        chip8.run_instruction(instruction);
+        chip8.run_instruction(instruction).unwrap();
 
         assert_eq!(chip8.registers.read_vx(0x01), 214);
     }
@@ -513,7 +1203,7 @@ mod test {
         chip8.registers.write_vx(0x01, 255);
 
         let instruction = Instruction::_7xkk(0x01, 2);
-        chip8.run_instruction(instruction);
+        chip8.run_instruction(instruction).unwrap();
 
         assert_eq!(chip8.registers.read_vx(0x01), 1);
     }
@@ -527,7 +1217,7 @@ mod test {
         chip8.registers.write_vx(0x02, 0xF1);
 
         let instruction = Instruction::_8xy0(0x01, 0x02);
-        chip8.run_instruction(instruction);
+        chip8.run_instruction(instruction).unwrap();
 
         assert_eq!(chip8.registers.read_vx(0x01), 0xF1);
     }
@@ -541,7 +1231,7 @@ mod test {
         chip8.registers.write_vx(0x02, 0b11100101);
 
         let instruction = Instruction::_8xy1(0x01, 0x02);
-        chip8.run_instruction(instruction);
+        chip8.run_instruction(instruction).unwrap();
 
         assert_eq!(chip8.registers.read_vx(0x01), 0b11110111);
     }
@@ -555,7 +1245,7 @@ mod test {
         chip8.registers.write_vx(0x02, 0b11100101);
 
         let instruction = Instruction::_8xy2(0x01, 0x02);
-        chip8.run_instruction(instruction);
+        chip8.run_instruction(instruction).unwrap();
 
         assert_eq!(chip8.registers.read_vx(0x01), 0b00100000);
     }
@@ -569,11 +1259,27 @@ mod test {
         chip8.registers.write_vx(0x02, 0b11100101);
 
         let instruction = Instruction::_8xy3(0x01, 0x02);
-        chip8.run_instruction(instruction);
+        chip8.run_instruction(instruction).unwrap();
 
         assert_eq!(chip8.registers.read_vx(0x01), 0b11010111);
     }
 
+    #[test]
+    fn xor_vf_reset() {
+        let mut display = DisplayMock {};
+        let mut chip8 = Chip8::with_quirks(&mut display, Quirks::COSMAC_VIP);
+
+        chip8.registers.write_vx(0x01, 0b00110010);
+        chip8.registers.write_vx(0x02, 0b11100101);
+        chip8.registers.write_vx(0x0F, 0x01);
+
+        let instruction = Instruction::_8xy3(0x01, 0x02);
+        chip8.run_instruction(instruction).unwrap();
+
+        assert_eq!(chip8.registers.read_vx(0x01), 0b11010111);
+        assert_eq!(chip8.registers.read_vx(0x0F), 0);
+    }
+
     #[test]
     fn sum() {
         let mut display = DisplayMock {};
@@ -583,7 +1289,7 @@ mod test {
         chip8.registers.write_vx(0x02, 23);
 
         let instruction = Instruction::_8xy4(0x01, 0x02);
-        chip8.run_instruction(instruction);
+        chip8.run_instruction(instruction).unwrap();
 
         assert_eq!(chip8.registers.read_vx(0x01), 237);
         assert_eq!(chip8.registers.read_vx(0x0F), 0);
@@ -598,7 +1304,7 @@ mod test {
         chip8.registers.write_vx(0x02, 3);
 
         let instruction = Instruction::_8xy4(0x01, 0x02);
-        chip8.run_instruction(instruction);
+        chip8.run_instruction(instruction).unwrap();
 
         assert_eq!(chip8.registers.read_vx(0x01), 2);
         assert_eq!(chip8.registers.read_vx(0x0F), 1);
@@ -613,7 +1319,7 @@ mod test {
         chip8.registers.write_vx(0x02, 23);
 
         let instruction = Instruction::_8xy5(0x01, 0x02);
-        chip8.run_instruction(instruction);
+        chip8.run_instruction(instruction).unwrap();
 
         assert_eq!(chip8.registers.read_vx(0x01), 191);
         assert_eq!(chip8.registers.read_vx(0x0F), 1);
@@ -628,12 +1334,60 @@ mod test {
         chip8.registers.write_vx(0x02, 216);
 
         let instruction = Instruction::_8xy5(0x01, 0x02);
-        chip8.run_instruction(instruction);
+        chip8.run_instruction(instruction).unwrap();
 
         assert_eq!(chip8.registers.read_vx(0x01), 254);
         assert_eq!(chip8.registers.read_vx(0x0F), 0);
     }
 
+    #[test]
+    fn subtract_equal_does_not_borrow() {
+        let mut display = DisplayMock {};
+        let mut chip8 = Chip8::new(&mut display);
+
+        chip8.registers.write_vx(0x01, 42);
+        chip8.registers.write_vx(0x02, 42);
+
+        chip8
+            .run_instruction(Instruction::_8xy5(0x01, 0x02))
+            .unwrap();
+        assert_eq!(chip8.registers.read_vx(0x01), 0);
+        assert_eq!(chip8.registers.read_vx(0x0F), 1);
+
+        chip8.registers.write_vx(0x01, 42);
+        chip8
+            .run_instruction(Instruction::_8xy7(0x01, 0x02))
+            .unwrap();
+        assert_eq!(chip8.registers.read_vx(0x01), 0);
+        assert_eq!(chip8.registers.read_vx(0x0F), 1);
+    }
+
+    #[test]
+    fn flag_overwrites_vf_result() {
+        let mut display = DisplayMock {};
+        let mut chip8 = Chip8::new(&mut display);
+
+        chip8.registers.write_vx(0x0F, 200);
+        chip8.registers.write_vx(0x01, 100);
+        chip8
+            .run_instruction(Instruction::_8xy4(0x0F, 0x01))
+            .unwrap();
+        assert_eq!(chip8.registers.read_vx(0x0F), 1);
+
+        chip8.registers.write_vx(0x0F, 5);
+        chip8.registers.write_vx(0x01, 3);
+        chip8
+            .run_instruction(Instruction::_8xy5(0x0F, 0x01))
+            .unwrap();
+        assert_eq!(chip8.registers.read_vx(0x0F), 1);
+
+        chip8.registers.write_vx(0x0F, 0b10000000);
+        chip8
+            .run_instruction(Instruction::_8xyE(0x0F, 0x0F))
+            .unwrap();
+        assert_eq!(chip8.registers.read_vx(0x0F), 1);
+    }
+
     #[test]
     fn shift_right() {
         let mut display = DisplayMock {};
@@ -641,8 +1395,8 @@ mod test {
 
         chip8.registers.write_vx(0x01, 0b00110100);
 
-        let instruction = Instruction::_8xy6(0x01);
-        chip8.run_instruction(instruction);
+        let instruction = Instruction::_8xy6(0x01, 0x02);
+        chip8.run_instruction(instruction).unwrap();
 
         assert_eq!(chip8.registers.read_vx(0x01), 0b00011010);
         assert_eq!(chip8.registers.read_vx(0x0F), 0);
@@ -655,8 +1409,23 @@ mod test {
 
         chip8.registers.write_vx(0x01, 0b00110101);
 
-        let instruction = Instruction::_8xy6(0x01);
-        chip8.run_instruction(instruction);
+        let instruction = Instruction::_8xy6(0x01, 0x02);
+        chip8.run_instruction(instruction).unwrap();
+
+        assert_eq!(chip8.registers.read_vx(0x01), 0b00011010);
+        assert_eq!(chip8.registers.read_vx(0x0F), 1);
+    }
+
+    #[test]
+    fn shift_right_uses_vy() {
+        let mut display = DisplayMock {};
+        let mut chip8 = Chip8::with_quirks(&mut display, Quirks::COSMAC_VIP);
+
+        chip8.registers.write_vx(0x01, 0b11111111);
+        chip8.registers.write_vx(0x02, 0b00110101);
+
+        let instruction = Instruction::_8xy6(0x01, 0x02);
+        chip8.run_instruction(instruction).unwrap();
 
         assert_eq!(chip8.registers.read_vx(0x01), 0b00011010);
         assert_eq!(chip8.registers.read_vx(0x0F), 1);
@@ -671,7 +1440,7 @@ mod test {
         chip8.registers.write_vx(0x02, 215);
 
         let instruction = Instruction::_8xy7(0x01, 0x02);
-        chip8.run_instruction(instruction);
+        chip8.run_instruction(instruction).unwrap();
 
         assert_eq!(chip8.registers.read_vx(0x01), 15);
         assert_eq!(chip8.registers.read_vx(0x0F), 1);
@@ -686,7 +1455,7 @@ mod test {
         chip8.registers.write_vx(0x02, 180);
 
         let instruction = Instruction::_8xy7(0x01, 0x02);
-        chip8.run_instruction(instruction);
+        chip8.run_instruction(instruction).unwrap();
 
         assert_eq!(chip8.registers.read_vx(0x01), 236);
         assert_eq!(chip8.registers.read_vx(0x0F), 0);
@@ -699,8 +1468,8 @@ mod test {
 
         chip8.registers.write_vx(0x01, 0b01001101);
 
-        let instruction = Instruction::_8xyE(0x01);
-        chip8.run_instruction(instruction);
+        let instruction = Instruction::_8xyE(0x01, 0x02);
+        chip8.run_instruction(instruction).unwrap();
 
         assert_eq!(chip8.registers.read_vx(0x01), 0b10011010);
         assert_eq!(chip8.registers.read_vx(0x0F), 0);
@@ -713,13 +1482,28 @@ mod test {
 
         chip8.registers.write_vx(0x01, 0b11001101);
 
-        let instruction = Instruction::_8xyE(0x01);
-        chip8.run_instruction(instruction);
+        let instruction = Instruction::_8xyE(0x01, 0x02);
+        chip8.run_instruction(instruction).unwrap();
 
         assert_eq!(chip8.registers.read_vx(0x01), 0b10011010);
         assert_eq!(chip8.registers.read_vx(0x0F), 1);
     }
 
+    #[test]
+    fn shift_left_uses_vy() {
+        let mut display = DisplayMock {};
+        let mut chip8 = Chip8::with_quirks(&mut display, Quirks::COSMAC_VIP);
+
+        chip8.registers.write_vx(0x01, 0b11111111);
+        chip8.registers.write_vx(0x02, 0b01001101);
+
+        let instruction = Instruction::_8xyE(0x01, 0x02);
+        chip8.run_instruction(instruction).unwrap();
+
+        assert_eq!(chip8.registers.read_vx(0x01), 0b10011010);
+        assert_eq!(chip8.registers.read_vx(0x0F), 0);
+    }
+
     #[test]
     fn skip_cmp_ne_skips() {
         let mut display = DisplayMock {};
@@ -729,7 +1513,7 @@ mod test {
         chip8.registers.write_vx(0x02, 0x0E);
 
         let instruction = Instruction::_9xy0(0x01, 0x02);
-        chip8.run_instruction(instruction);
+        chip8.run_instruction(instruction).unwrap();
 
         assert_eq!(chip8.program_counter, 4);
     }
@@ -743,18 +1527,32 @@ mod test {
         chip8.registers.write_vx(0x02, 0x0F);
 
         let instruction = Instruction::_9xy0(0x01, 0x02);
-        chip8.run_instruction(instruction);
+        chip8.run_instruction(instruction).unwrap();
 
         assert_eq!(chip8.program_counter, 2);
     }
 
+    #[test]
+    fn long_load() {
+        let mut display = DisplayMock {};
+        let mut chip8 = Chip8::new(&mut display);
+
+        chip8
+            .load_rom_bytes(&[0xF0, 0x00, 0xAB, 0xCD, 0x6A, 0x2A])
+            .unwrap();
+        chip8.step().unwrap();
+
+        assert_eq!(chip8.registers.read_i(), 0xABCD);
+        assert_eq!(chip8.program_counter, PROGRAM_OFFSET + 4);
+    }
+
     #[test]
     fn set_addr() {
         let mut display = DisplayMock {};
         let mut chip8 = Chip8::new(&mut display);
 
         let instruction = Instruction::_Annn(0x140F);
-        chip8.run_instruction(instruction);
+        chip8.run_instruction(instruction).unwrap();
 
         assert_eq!(chip8.registers.read_i(), 0x140F);
     }
@@ -767,11 +1565,25 @@ mod test {
         chip8.registers.write_vx(0x00, 0x13);
 
         let instruction = Instruction::_Bnnn(0x23);
-        chip8.run_instruction(instruction);
+        chip8.run_instruction(instruction).unwrap();
 
         assert_eq!(chip8.program_counter, 0x13 + 0x23);
     }
 
+    #[test]
+    fn jump_with_vx() {
+        let mut display = DisplayMock {};
+        let mut chip8 = Chip8::with_quirks(&mut display, Quirks::SUPER_CHIP);
+
+        chip8.registers.write_vx(0x00, 0x13);
+        chip8.registers.write_vx(0x02, 0x05);
+
+        let instruction = Instruction::_Bnnn(0x230);
+        chip8.run_instruction(instruction).unwrap();
+
+        assert_eq!(chip8.program_counter, 0x230 + 0x05);
+    }
+
     #[test]
     fn draw() {
         let mut display = DisplayMock {};
@@ -779,23 +1591,128 @@ mod test {
 
         // draw first letter from font
         let instruction = Instruction::_Dxyn(0, 0, 5);
-        chip8.run_instruction(instruction);
+        chip8.run_instruction(instruction).unwrap();
 
-        assert_eq!(&chip8.display_state.as_bytes()[0], &FONT_SPRITES[0]);
-        assert_eq!(&chip8.display_state.as_bytes()[8], &FONT_SPRITES[1]);
-        assert_eq!(&chip8.display_state.as_bytes()[16], &FONT_SPRITES[2]);
-        assert_eq!(&chip8.display_state.as_bytes()[24], &FONT_SPRITES[3]);
-        assert_eq!(&chip8.display_state.as_bytes()[32], &FONT_SPRITES[4]);
+        assert_eq!(&chip8.display_state.plane(0)[0], &FONT_SPRITES[0]);
+        assert_eq!(&chip8.display_state.plane(0)[8], &FONT_SPRITES[1]);
+        assert_eq!(&chip8.display_state.plane(0)[16], &FONT_SPRITES[2]);
+        assert_eq!(&chip8.display_state.plane(0)[24], &FONT_SPRITES[3]);
+        assert_eq!(&chip8.display_state.plane(0)[32], &FONT_SPRITES[4]);
 
         // draw same sprite -> should flip bits
         let instruction = Instruction::_Dxyn(0, 0, 5);
-        chip8.run_instruction(instruction);
+        chip8.run_instruction(instruction).unwrap();
+
+        assert_eq!(&chip8.display_state.plane(0)[0], &0x00);
+        assert_eq!(&chip8.display_state.plane(0)[8], &0x00);
+        assert_eq!(&chip8.display_state.plane(0)[16], &0x00);
+        assert_eq!(&chip8.display_state.plane(0)[24], &0x00);
+        assert_eq!(&chip8.display_state.plane(0)[32], &0x00);
+    }
 
-        assert_eq!(&chip8.display_state.as_bytes()[0], &0x00);
-        assert_eq!(&chip8.display_state.as_bytes()[8], &0x00);
-        assert_eq!(&chip8.display_state.as_bytes()[16], &0x00);
-        assert_eq!(&chip8.display_state.as_bytes()[24], &0x00);
-        assert_eq!(&chip8.display_state.as_bytes()[32], &0x00);
+    #[test]
+    fn draw_clips() {
+        let mut display = DisplayMock {};
+        let mut chip8 = Chip8::new(&mut display);
+
+        // draw first letter from font in the bottom right corner
+        chip8.registers.write_vx(0x00, DISPLAY_COLS as u8 - 4);
+        chip8.registers.write_vx(0x01, DISPLAY_ROWS as u8 - 2);
+        let instruction = Instruction::_Dxyn(0x00, 0x01, 5);
+        chip8.run_instruction(instruction).unwrap();
+
+        assert!(chip8
+            .display_state
+            .is_on(0, DISPLAY_COLS - 4, DISPLAY_ROWS - 2));
+        assert!(!chip8.display_state.is_on(0, 0, DISPLAY_ROWS - 2));
+        assert!(!chip8.display_state.is_on(0, DISPLAY_COLS - 4, 0));
+    }
+
+    #[test]
+    fn draw_wraps() {
+        let mut display = DisplayMock {};
+        let mut chip8 = Chip8::with_quirks(&mut display, Quirks::MODERN);
+
+        // draw first letter from font in the bottom right corner
+        chip8.registers.write_vx(0x00, DISPLAY_COLS as u8 - 4);
+        chip8.registers.write_vx(0x01, DISPLAY_ROWS as u8 - 2);
+        let instruction = Instruction::_Dxyn(0x00, 0x01, 5);
+        chip8.run_instruction(instruction).unwrap();
+
+        assert!(chip8
+            .display_state
+            .is_on(0, DISPLAY_COLS - 4, DISPLAY_ROWS - 2));
+        // the top row of the 0 glyph is 0xF0, so nothing wraps horizontally
+        assert!(!chip8.display_state.is_on(0, 0, DISPLAY_ROWS - 2));
+        // the last row of the glyph wraps to the top of the screen
+        assert!(chip8.display_state.is_on(0, DISPLAY_COLS - 4, 2));
+    }
+
+    #[test]
+    fn draw_display_wait() {
+        let mut display = DisplayMock {};
+        let mut chip8 = Chip8::with_quirks(&mut display, Quirks::COSMAC_VIP);
+
+        let instruction = Instruction::_Dxyn(0, 0, 5);
+        chip8.run_instruction(instruction).unwrap();
+        assert_eq!(chip8.program_counter, 2);
+
+        // a second draw in the same frame waits
+        let instruction = Instruction::_Dxyn(0, 0, 5);
+        chip8.run_instruction(instruction).unwrap();
+        assert_eq!(chip8.program_counter, 2);
+    }
+
+    #[test]
+    fn draw_large_sprite() {
+        let mut display = DisplayMock {};
+        let mut chip8 = Chip8::new(&mut display);
+
+        let instruction = Instruction::_00FF;
+        chip8.run_instruction(instruction).unwrap();
+
+        let sprite: Vec<u8> = (0..32)
+            .map(|i| if i % 2 == 0 { 0xFF } else { 0x01 })
+            .collect();
+        chip8.ram.write_bytes(PROGRAM_OFFSET, &sprite).unwrap();
+        chip8.registers.write_i(PROGRAM_OFFSET);
+        let instruction = Instruction::_Dxyn(0x00, 0x00, 0);
+        chip8.run_instruction(instruction).unwrap();
+
+        for row in 0..16 {
+            assert_eq!(chip8.display_state.plane(0)[row * 16], 0xFF);
+            assert_eq!(chip8.display_state.plane(0)[row * 16 + 1], 0x01);
+        }
+        assert!(!chip8.display_state.is_on(0, 0, 16));
+        assert_eq!(chip8.registers.read_vx(0x0F), 0);
+    }
+
+    #[test]
+    fn draw_both_planes() {
+        let mut display = DisplayMock {};
+        let mut chip8 = Chip8::new(&mut display);
+
+        chip8
+            .ram
+            .write_bytes(PROGRAM_OFFSET, &[0xF0, 0x0F])
+            .unwrap();
+        chip8.registers.write_i(PROGRAM_OFFSET);
+        let instruction = Instruction::_Fn01(0b11);
+        chip8.run_instruction(instruction).unwrap();
+        let instruction = Instruction::_Dxyn(0, 0, 1);
+        chip8.run_instruction(instruction).unwrap();
+
+        assert_eq!(chip8.display_state.plane(0)[0], 0xF0);
+        assert_eq!(chip8.display_state.plane(1)[0], 0x0F);
+
+        // clearing only affects the selected plane
+        let instruction = Instruction::_Fn01(0b10);
+        chip8.run_instruction(instruction).unwrap();
+        let instruction = Instruction::_00E0;
+        chip8.run_instruction(instruction).unwrap();
+
+        assert_eq!(chip8.display_state.plane(0)[0], 0xF0);
+        assert_eq!(chip8.display_state.plane(1)[0], 0x00);
     }
 
     #[test]
@@ -809,25 +1726,46 @@ mod test {
         let mut chip8 = Chip8::new(&mut display);
 
         let instruction = Instruction::_Cxkk(0x01, 255);
-        chip8.run_instruction(instruction);
+        chip8.run_instruction(instruction).unwrap();
         let first = chip8.registers.read_vx(0x01);
 
         let instruction = Instruction::_Cxkk(0x01, 255);
-        chip8.run_instruction(instruction);
+        chip8.run_instruction(instruction).unwrap();
         let second = chip8.registers.read_vx(0x01);
 
         assert_ne!(first, second);
     }
 
+    #[test]
+    fn rnd_seeded() {
+        let mut display = DisplayMock {};
+        let mut chip8 = Chip8::new(&mut display);
+        let mut draws = |seed| {
+            chip8.set_seed(seed);
+            (0..8)
+                .map(|_| {
+                    chip8
+                        .run_instruction(Instruction::_Cxkk(0x01, 255))
+                        .unwrap();
+                    chip8.registers.read_vx(0x01)
+                })
+                .collect::<Vec<u8>>()
+        };
+
+        let first = draws(42);
+        assert_eq!(draws(42), first);
+        assert_ne!(draws(43), first);
+    }
+
     #[test]
     fn skip_if_key_skips() {
         let mut display = DisplayMock {};
         let mut chip8 = Chip8::new(&mut display);
 
         chip8.registers.write_vx(0x01, 0xA);
-        chip8.register_key(0xA);
+        chip8.press_key(0xA);
         let instruction = Instruction::_Ex9E(0x01);
-        chip8.run_instruction(instruction);
+        chip8.run_instruction(instruction).unwrap();
 
         assert_eq!(chip8.program_counter, 4);
     }
@@ -838,9 +1776,9 @@ mod test {
         let mut chip8 = Chip8::new(&mut display);
 
         chip8.registers.write_vx(0x01, 0xA);
-        chip8.register_key(0xB);
+        chip8.press_key(0xB);
         let instruction = Instruction::_Ex9E(0x01);
-        chip8.run_instruction(instruction);
+        chip8.run_instruction(instruction).unwrap();
 
         assert_eq!(chip8.program_counter, 2);
     }
@@ -851,9 +1789,9 @@ mod test {
         let mut chip8 = Chip8::new(&mut display);
 
         chip8.registers.write_vx(0x01, 0x0A);
-        chip8.register_key(0xB);
+        chip8.press_key(0xB);
         let instruction = Instruction::_ExA1(0x01);
-        chip8.run_instruction(instruction);
+        chip8.run_instruction(instruction).unwrap();
 
         assert_eq!(chip8.program_counter, 4);
     }
@@ -864,9 +1802,9 @@ mod test {
         let mut chip8 = Chip8::new(&mut display);
 
         chip8.registers.write_vx(0x01, 0x0A);
-        chip8.register_key(0xA);
+        chip8.press_key(0xA);
         let instruction = Instruction::_ExA1(0x01);
-        chip8.run_instruction(instruction);
+        chip8.run_instruction(instruction).unwrap();
 
         assert_eq!(chip8.program_counter, 2);
     }
@@ -876,9 +1814,37 @@ mod test {
         let mut display = DisplayMock {};
         let mut chip8 = Chip8::new(&mut display);
 
-        chip8.current_key = None;
         let instruction = Instruction::_Fx0A(0x01);
-        chip8.run_instruction(instruction);
+        chip8.run_instruction(instruction).unwrap();
+
+        assert_eq!(chip8.program_counter, 0);
+    }
+
+    #[test]
+    fn wait_for_key_waits_for_release() {
+        let mut display = DisplayMock {};
+        let mut chip8 = Chip8::new(&mut display);
+
+        let instruction = Instruction::_Fx0A(0x01);
+        chip8.run_instruction(instruction).unwrap();
+        chip8.press_key(0x0F);
+        let instruction = Instruction::_Fx0A(0x01);
+        chip8.run_instruction(instruction).unwrap();
+
+        assert_eq!(chip8.program_counter, 0);
+    }
+
+    #[test]
+    fn wait_for_key_ignores_held_key() {
+        let mut display = DisplayMock {};
+        let mut chip8 = Chip8::new(&mut display);
+
+        chip8.press_key(0x0F);
+        let instruction = Instruction::_Fx0A(0x01);
+        chip8.run_instruction(instruction).unwrap();
+        chip8.release_key(0x0F);
+        let instruction = Instruction::_Fx0A(0x01);
+        chip8.run_instruction(instruction).unwrap();
 
         assert_eq!(chip8.program_counter, 0);
     }
@@ -888,14 +1854,139 @@ mod test {
         let mut display = DisplayMock {};
         let mut chip8 = Chip8::new(&mut display);
 
-        chip8.current_key = Some(0x0F);
         let instruction = Instruction::_Fx0A(0x01);
-        chip8.run_instruction(instruction);
+        chip8.run_instruction(instruction).unwrap();
+        chip8.press_key(0x0F);
+        chip8.release_key(0x0F);
+        let instruction = Instruction::_Fx0A(0x01);
+        chip8.run_instruction(instruction).unwrap();
 
         assert_eq!(chip8.program_counter, 2);
         assert_eq!(chip8.registers.read_vx(0x01), 0x0F);
     }
 
+    #[test]
+    fn release_key() {
+        let mut display = DisplayMock {};
+        let mut chip8 = Chip8::new(&mut display);
+
+        chip8.registers.write_vx(0x01, 0xA);
+        chip8.press_key(0xA);
+        chip8.press_key(0xB);
+        chip8.release_key(0xA);
+        let instruction = Instruction::_Ex9E(0x01);
+        chip8.run_instruction(instruction).unwrap();
+
+        assert_eq!(chip8.program_counter, 2);
+        assert!(chip8.is_pressed(0xB));
+    }
+
+    #[test]
+    fn ignores_keys_out_of_range() {
+        let mut display = DisplayMock {};
+        let mut chip8 = Chip8::new(&mut display);
+
+        let instruction = Instruction::_Fx0A(0x01);
+        chip8.run_instruction(instruction).unwrap();
+        chip8.press_key(0x10);
+        chip8.release_key(0xFF);
+        assert!(!chip8.is_pressed(0x10));
+
+        chip8.registers.write_vx(0x02, 0x10);
+        let instruction = Instruction::_ExA1(0x02);
+        chip8.run_instruction(instruction).unwrap();
+
+        assert_eq!(chip8.program_counter, 4);
+        assert_eq!(chip8.key_wait, KeyWait::Waiting);
+    }
+
+    #[test]
+    fn beeps_while_sound_timer_runs() {
+        let mut display = DisplayMock {};
+        let mut audio = AudioMock { beeping: vec![] };
+        let mut chip8 = Chip8::new(&mut display);
+        chip8.set_audio(&mut audio);
+
+        // LD V1, 2; LD ST, V1; JP 0x204
+        chip8
+            .load_rom_bytes(&[0x61, 0x02, 0xF1, 0x18, 0x12, 0x04])
+            .unwrap();
+        for _ in 0..3 {
+            chip8.run_frame().unwrap();
+        }
+        drop(chip8);
+
+        assert_eq!(audio.beeping, vec![true, false, false]);
+    }
+
+    #[test]
+    fn draws_only_changed_frames() {
+        let mut display = FramesMock { drawn: vec![] };
+        let mut chip8 = Chip8::new(&mut display);
+        chip8.set_ipf(1);
+
+        // CLS; JP 0x204; DRW V0, V0, 1; JP 0x206
+        chip8
+            .load_rom_bytes(&[0x00, 0xE0, 0x12, 0x04, 0xD0, 0x01, 0x12, 0x06])
+            .unwrap();
+        for _ in 0..5 {
+            chip8.run_frame().unwrap();
+        }
+        drop(chip8);
+
+        assert_eq!(display.drawn, vec![true, false, true, false, false]);
+    }
+
+    #[test]
+    fn run_frame() {
+        let mut display = DisplayMock {};
+        let mut chip8 = Chip8::new(&mut display);
+        chip8.set_ipf(3);
+
+        // LD V1, 5; LD DT, V1; ADD V2, 1; JP 0x204
+        chip8
+            .load_rom_bytes(&[0x61, 0x05, 0xF1, 0x15, 0x72, 0x01, 0x12, 0x04])
+            .unwrap();
+        chip8.run_frame().unwrap();
+        assert_eq!(chip8.registers.read_delay_timer(), 4);
+        assert_eq!(chip8.registers.read_vx(0x02), 1);
+
+        chip8.run_frame().unwrap();
+        assert_eq!(chip8.registers.read_delay_timer(), 3);
+        assert_eq!(chip8.registers.read_vx(0x02), 2);
+    }
+
+    #[test]
+    fn load_audio_pattern() {
+        let mut display = DisplayMock {};
+        let mut audio = NullAudio::new();
+        let mut chip8 = Chip8::new(&mut display);
+        chip8.set_audio(&mut audio);
+
+        let pattern: Vec<u8> = (0..16).collect();
+        chip8.ram.write_bytes(PROGRAM_OFFSET, &pattern).unwrap();
+        chip8.registers.write_i(PROGRAM_OFFSET);
+        chip8.registers.write_vx(0x01, 0x70);
+
+        // the pitch is kept until there is a pattern to play
+        let instruction = Instruction::_Fx3A(0x01);
+        chip8.run_instruction(instruction).unwrap();
+        assert_eq!(chip8.pitch(), 0x70);
+        assert_eq!(chip8.audio_pattern(), None);
+
+        let instruction = Instruction::_F002;
+        chip8.run_instruction(instruction).unwrap();
+        assert_eq!(
+            chip8.audio_pattern().unwrap().as_slice(),
+            pattern.as_slice()
+        );
+
+        drop(chip8);
+        let (played, pitch) = audio.pattern().unwrap();
+        assert_eq!(played.as_slice(), pattern.as_slice());
+        assert_eq!(pitch, 0x70);
+    }
+
     #[test]
     fn set_delay_timer() {
         let mut display = DisplayMock {};
@@ -903,7 +1994,7 @@ mod test {
 
         chip8.registers.write_vx(0x01, 30);
         let instruction = Instruction::_Fx15(0x01);
-        chip8.run_instruction(instruction);
+        chip8.run_instruction(instruction).unwrap();
 
         assert_eq!(chip8.registers.read_delay_timer(), 30);
     }
@@ -915,7 +2006,7 @@ mod test {
 
         chip8.registers.write_vx(0x01, 30);
         let instruction = Instruction::_Fx18(0x01);
-        chip8.run_instruction(instruction);
+        chip8.run_instruction(instruction).unwrap();
 
         assert_eq!(chip8.registers.read_sound_timer(), 30);
     }
@@ -928,7 +2019,7 @@ mod test {
         chip8.registers.write_vx(0x01, 0x0004);
         chip8.registers.write_i(0x000F);
         let instruction = Instruction::_Fx29(0x01);
-        chip8.run_instruction(instruction);
+        chip8.run_instruction(instruction).unwrap();
 
         assert_eq!(chip8.registers.read_i(), 0x0014);
     }
@@ -941,11 +2032,27 @@ mod test {
         // the F sprite starts at byte 75
         chip8.registers.write_vx(0x01, 0x0F);
         let instruction = Instruction::_Fx29(0x01);
-        chip8.run_instruction(instruction);
+        chip8.run_instruction(instruction).unwrap();
 
         assert_eq!(chip8.registers.read_i(), 75);
     }
 
+    #[test]
+    fn set_big_font_addr() {
+        let mut display = DisplayMock {};
+        let mut chip8 = Chip8::new(&mut display);
+
+        chip8.registers.write_vx(0x01, 0x02);
+        let instruction = Instruction::_Fx30(0x01);
+        chip8.run_instruction(instruction).unwrap();
+
+        assert_eq!(chip8.registers.read_i(), BIG_FONT_OFFSET + 20);
+        assert_eq!(
+            chip8.ram.read_bytes(chip8.registers.read_i(), 10).unwrap(),
+            &BIG_FONT_SPRITES[20..30]
+        );
+    }
+
     #[test]
     fn binary_coded_decimal() {
         let mut display = DisplayMock {};
@@ -953,12 +2060,12 @@ mod test {
 
         chip8.registers.write_vx(0x01, 123);
         let instruction = Instruction::_Fx33(0x01);
-        chip8.run_instruction(instruction);
+        chip8.run_instruction(instruction).unwrap();
 
         let expected: [u8; 3] = [1, 2, 3];
 
         assert_eq!(
-            chip8.ram.read_bytes(chip8.registers.read_i() as usize, 3),
+            chip8.ram.read_bytes(chip8.registers.read_i(), 3).unwrap(),
             &expected
         );
     }
@@ -975,12 +2082,12 @@ mod test {
         chip8.registers.write_vx(0x03, 0x07);
 
         let instruction = Instruction::_Fx55(0x03);
-        chip8.run_instruction(instruction);
+        chip8.run_instruction(instruction).unwrap();
 
         let expected: [u8; 5] = [0x0A, 0x0F, 0x01, 0x07, 0x00]; // last byte unaffected
 
         assert_eq!(
-            chip8.ram.read_bytes(chip8.registers.read_i() as usize, 5),
+            chip8.ram.read_bytes(chip8.registers.read_i(), 5).unwrap(),
             &expected
         );
     }
@@ -993,9 +2100,10 @@ mod test {
         chip8.registers.write_i(PROGRAM_OFFSET);
         chip8
             .ram
-            .write_bytes(PROGRAM_OFFSET, &[0x04, 0x0F, 0x0A, 0x07]);
+            .write_bytes(PROGRAM_OFFSET, &[0x04, 0x0F, 0x0A, 0x07])
+            .unwrap();
         let instruction = Instruction::_Fx65(0x03);
-        chip8.run_instruction(instruction);
+        chip8.run_instruction(instruction).unwrap();
 
         assert_eq!(chip8.registers.read_vx(0x00), 0x04);
         assert_eq!(chip8.registers.read_vx(0x01), 0x0F);
@@ -1003,4 +2111,51 @@ mod test {
         assert_eq!(chip8.registers.read_vx(0x03), 0x07);
         assert_eq!(chip8.registers.read_vx(0x04), 0x00); // <-- should be unaffacted
     }
+
+    #[test]
+    fn load_store_increments_i() {
+        let mut display = DisplayMock {};
+        let mut chip8 = Chip8::with_quirks(&mut display, Quirks::COSMAC_VIP);
+
+        chip8.registers.write_i(PROGRAM_OFFSET);
+        let instruction = Instruction::_Fx55(0x03);
+        chip8.run_instruction(instruction).unwrap();
+        assert_eq!(chip8.registers.read_i(), PROGRAM_OFFSET + 4);
+
+        let instruction = Instruction::_Fx65(0x01);
+        chip8.run_instruction(instruction).unwrap();
+        assert_eq!(chip8.registers.read_i(), PROGRAM_OFFSET + 6);
+    }
+
+    #[test]
+    fn load_store_stops_short() {
+        let mut display = DisplayMock {};
+        let mut chip8 = Chip8::with_quirks(&mut display, Quirks::CHIP_48);
+
+        chip8.registers.write_i(PROGRAM_OFFSET);
+        let instruction = Instruction::_Fx55(0x03);
+        chip8.run_instruction(instruction).unwrap();
+        assert_eq!(chip8.registers.read_i(), PROGRAM_OFFSET + 3);
+    }
+
+    #[test]
+    fn save_and_restore_rpl_flags() {
+        let mut display = DisplayMock {};
+        let mut chip8 = Chip8::new(&mut display);
+
+        chip8.registers.write_vx(0x00, 0x0A);
+        chip8.registers.write_vx(0x01, 0x0F);
+        chip8.registers.write_vx(0x02, 0x01);
+        let instruction = Instruction::_Fx75(0x01);
+        chip8.run_instruction(instruction).unwrap();
+
+        chip8.registers.write_vx(0x00, 0x00);
+        chip8.registers.write_vx(0x01, 0x00);
+        let instruction = Instruction::_Fx85(0x02);
+        chip8.run_instruction(instruction).unwrap();
+
+        assert_eq!(chip8.registers.read_vx(0x00), 0x0A);
+        assert_eq!(chip8.registers.read_vx(0x01), 0x0F);
+        assert_eq!(chip8.registers.read_vx(0x02), 0x00);
+    }
 }
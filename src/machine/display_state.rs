@@ -1,19 +1,104 @@
 use crate::bit_utils::get_bit_from_byte;
+use std::fmt::{Display, Formatter};
 
+/// Number of bitplanes; XO-CHIP draws in up to two of them.
+pub const PLANES: usize = 2;
+
+/// The framebuffer, made up of [`PLANES`] 1bpp bitplanes. Each plane is packed row by row with the
+/// most significant bit of each byte first. A pixel's colour (0 - 3) has bit n set if it is on in
+/// plane n.
+///
+/// Clearing, scrolling and drawing only affect the selected planes; Chip-8 and SUPER-CHIP programs
+/// only ever use the first.
+///
+/// The state is marked dirty whenever it is changed, so frontends can tell when it needs to be
+/// presented again.
 pub struct DisplayState {
-    raw: Vec<u8>,
+    planes: [Vec<u8>; PLANES],
+    selected: u8,
     cols: usize,
     rows: usize,
+    dirty: bool,
 }
 
 impl DisplayState {
     pub fn new(cols: usize, rows: usize) -> Self {
-        let raw: Vec<u8> = vec![0x00; cols * rows / 8];
-        DisplayState { cols, rows, raw }
+        DisplayState {
+            cols,
+            rows,
+            planes: [vec![0x00; cols * rows / 8], vec![0x00; cols * rows / 8]],
+            selected: 0b01,
+            dirty: true,
+        }
     }
 
+    /// Clear the selected planes.
     pub fn clear(&mut self) {
-        self.raw = vec![0x00; self.cols * self.rows / 8];
+        for plane in self.selected_planes() {
+            self.planes[plane].fill(0x00);
+        }
+        self.dirty = true;
+    }
+
+    /// Change the resolution; this clears every plane.
+    pub fn resize(&mut self, cols: usize, rows: usize) {
+        self.cols = cols;
+        self.rows = rows;
+        self.planes = [vec![0x00; cols * rows / 8], vec![0x00; cols * rows / 8]];
+        self.dirty = true;
+    }
+
+    /// Select the planes that subsequent operations act on; bit n of `mask` selects plane n.
+    pub fn select_planes(&mut self, mask: u8) {
+        self.selected = mask & 0b11;
+    }
+
+    /// Indices of the selected planes, in ascending order.
+    pub fn selected_planes(&self) -> impl Iterator<Item = usize> {
+        let selected = self.selected;
+        (0..PLANES).filter(move |plane| selected & (1 << plane) != 0)
+    }
+
+    /// Move every pixel `n` rows down; rows scrolled in at the top are blank.
+    pub fn scroll_down(&mut self, n: usize) {
+        let row_bytes = self.cols / 8;
+        for plane in self.selected_planes() {
+            let raw = &mut self.planes[plane];
+            let shift = (n * row_bytes).min(raw.len());
+            raw.rotate_right(shift);
+            raw[..shift].fill(0x00);
+        }
+        self.dirty = true;
+    }
+
+    /// Move every pixel `n` columns to the right; columns scrolled in on the left are blank.
+    pub fn scroll_right(&mut self, n: usize) {
+        self.scroll_horizontal(|x| x.checked_sub(n));
+    }
+
+    /// Move every pixel `n` columns to the left; columns scrolled in on the right are blank.
+    pub fn scroll_left(&mut self, n: usize) {
+        self.scroll_horizontal(|x| Some(x + n));
+    }
+
+    // `source` maps a destination column to the column its pixel is taken from.
+    fn scroll_horizontal(&mut self, source: impl Fn(usize) -> Option<usize>) {
+        for plane in self.selected_planes() {
+            let mut scrolled = vec![0x00; self.planes[plane].len()];
+            for y in 0..self.rows {
+                for x in 0..self.cols {
+                    match source(x) {
+                        Some(src_x) if src_x < self.cols && self.is_on(plane, src_x, y) => {
+                            let (byte_idx, bit_idx) = self.identify(x, y);
+                            scrolled[byte_idx] ^= 0b10000000 >> bit_idx;
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            self.planes[plane] = scrolled;
+        }
+        self.dirty = true;
     }
 
     fn identify(&self, x: usize, y: usize) -> (usize, usize) {
@@ -23,19 +108,66 @@ impl DisplayState {
         (byte_idx, bit_in_byte)
     }
 
-    pub fn is_on(&mut self, x: usize, y: usize) -> bool {
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn is_on(&self, plane: usize, x: usize, y: usize) -> bool {
         let (byte_idx, bit_idx) = self.identify(x, y);
-        get_bit_from_byte(bit_idx, &self.raw[byte_idx])
+        get_bit_from_byte(bit_idx, &self.planes[plane][byte_idx])
     }
 
-    pub fn flip(&mut self, x: usize, y: usize) {
+    pub fn flip(&mut self, plane: usize, x: usize, y: usize) {
         let (byte_idx, bit_idx) = self.identify(x, y);
         let mask = 0b10000000 >> bit_idx;
-        self.raw[byte_idx] ^= mask;
+        self.planes[plane][byte_idx] ^= mask;
+        self.dirty = true;
+    }
+
+    /// Whether the state changed since it was last marked clean (or created).
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    /// Mark the state as presented.
+    pub fn mark_clean(&mut self) {
+        self.dirty = false;
+    }
+
+    /// The colour (0 - 3) of a pixel, combining all planes.
+    pub fn pixel(&self, x: usize, y: usize) -> u8 {
+        (0..PLANES).fold(0, |colour, plane| {
+            colour | (self.is_on(plane, x, y) as u8) << plane
+        })
+    }
+
+    pub fn plane(&self, plane: usize) -> &[u8] {
+        &self.planes[plane]
     }
 
-    pub fn as_bytes(&self) -> &[u8] {
-        &self.raw
+    pub fn planes(&self) -> [&[u8]; PLANES] {
+        [&self.planes[0], &self.planes[1]]
+    }
+}
+
+// One character per pixel colour.
+const PIXEL_CHARS: [char; 4] = ['.', '#', '+', '@'];
+
+/// Renders the framebuffer as text, one line per row, with `.` for pixels that are off and `#`
+/// for pixels that are on. Pixels on in the second plane only are `+`, in both planes `@`.
+impl Display for DisplayState {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for y in 0..self.rows {
+            for x in 0..self.cols {
+                write!(f, "{}", PIXEL_CHARS[self.pixel(x, y) as usize])?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
     }
 }
 
@@ -46,11 +178,92 @@ mod test {
     #[test]
     fn flip() {
         let mut s = DisplayState::new(8, 1);
-        s.flip(4, 0);
-        assert_eq!(s.as_bytes()[0], 0b0001000);
+        s.flip(0, 4, 0);
+        assert_eq!(s.plane(0)[0], 0b0001000);
+
+        s.flip(0, 4, 0);
+        assert_eq!(s.plane(0)[0], 0b0000000);
+    }
+
+    #[test]
+    fn dirty() {
+        let mut s = DisplayState::new(16, 2);
+        assert!(s.is_dirty());
+        s.mark_clean();
+        assert!(!s.is_dirty());
+
+        s.flip(0, 1, 1);
+        assert!(s.is_dirty());
+        s.mark_clean();
+        s.scroll_left(4);
+        assert!(s.is_dirty());
+        s.mark_clean();
+        s.clear();
+        assert!(s.is_dirty());
+        s.mark_clean();
+        s.resize(16, 2);
+        assert!(s.is_dirty());
+    }
+
+    #[test]
+    fn resize() {
+        let mut s = DisplayState::new(64, 32);
+        s.flip(0, 0, 0);
+        s.resize(128, 64);
+        assert_eq!(s.cols(), 128);
+        assert_eq!(s.rows(), 64);
+        assert_eq!(s.plane(0), &[0x00; 128 * 64 / 8]);
+    }
+
+    #[test]
+    fn scroll_down() {
+        let mut s = DisplayState::new(16, 4);
+        s.flip(0, 3, 0);
+        s.flip(0, 9, 2);
+        s.scroll_down(1);
+        assert_eq!(
+            s.plane(0),
+            &[0x00, 0x00, 0b00010000, 0x00, 0x00, 0x00, 0x00, 0b01000000]
+        );
+    }
+
+    #[test]
+    fn scroll_horizontal() {
+        let mut s = DisplayState::new(16, 1);
+        s.flip(0, 0, 0);
+        s.flip(0, 14, 0);
+        s.scroll_right(4);
+        assert_eq!(s.plane(0), &[0b00001000, 0b00000000]);
+
+        s.scroll_left(4);
+        assert_eq!(s.plane(0), &[0b10000000, 0b00000000]);
+    }
+
+    #[test]
+    fn selected_planes() {
+        let mut s = DisplayState::new(8, 1);
+        s.flip(0, 0, 0);
+        s.flip(1, 0, 0);
+        s.flip(1, 1, 0);
+        assert_eq!(s.pixel(0, 0), 3);
+        assert_eq!(s.pixel(1, 0), 2);
+        assert_eq!(s.pixel(2, 0), 0);
 
-        s.flip(4, 0);
-        assert_eq!(s.as_bytes()[0], 0b0000000);
+        s.select_planes(0b10);
+        assert_eq!(s.selected_planes().collect::<Vec<_>>(), vec![1]);
+        s.clear();
+        assert_eq!(s.plane(0), &[0b10000000]);
+        assert_eq!(s.plane(1), &[0x00]);
+    }
+
+    #[test]
+    fn to_string() {
+        let mut s = DisplayState::new(8, 2);
+        s.flip(0, 1, 0);
+        s.flip(1, 2, 1);
+        s.flip(0, 7, 1);
+        s.flip(1, 7, 1);
+        assert_eq!(s.to_string(), ".#......\n..+....@\n");
     }
 
     #[test]
@@ -0,0 +1,64 @@
+use std::fmt::{Display, Formatter};
+
+/// Everything that can go wrong while loading or running a ROM.
+#[derive(Debug)]
+pub enum Chip8Error {
+    /// A return (00EE) was executed with an empty call stack.
+    StackUnderflow,
+    /// A call (2nnn) was executed with a full call stack.
+    StackOverflow,
+    /// The word at `addr` does not decode to a known instruction.
+    InvalidOpcode {
+        addr: usize,
+        opcode: u16,
+    },
+    /// An access of `len` bytes starting at `addr` falls outside of memory.
+    MemoryOutOfBounds {
+        addr: usize,
+        len: usize,
+    },
+    /// The ROM does not fit in the memory available to programs.
+    RomTooLarge {
+        size: usize,
+        max: usize,
+    },
+    Io(std::io::Error),
+}
+
+impl Display for Chip8Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Chip8Error::StackUnderflow => write!(f, "stack underflow: return without call"),
+            Chip8Error::StackOverflow => write!(f, "stack overflow: too many nested calls"),
+            Chip8Error::InvalidOpcode { addr, opcode } => {
+                write!(f, "invalid opcode {:#06X} at {:#06X}", opcode, addr)
+            }
+            Chip8Error::MemoryOutOfBounds { addr, len } => {
+                write!(
+                    f,
+                    "memory access out of bounds: {} bytes at {:#06X}",
+                    len, addr
+                )
+            }
+            Chip8Error::RomTooLarge { size, max } => {
+                write!(f, "rom too large: {} bytes (max {})", size, max)
+            }
+            Chip8Error::Io(e) => write!(f, "io error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for Chip8Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Chip8Error::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for Chip8Error {
+    fn from(e: std::io::Error) -> Self {
+        Chip8Error::Io(e)
+    }
+}
@@ -1,12 +1,23 @@
-#[derive(Debug)]
+use std::fmt::{Display, Formatter};
+
+/// A decoded Chip-8 instruction, named after its opcode pattern.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Instruction {
+    _00Cn(u8),
     _00E0,
     _00EE,
+    _00FB,
+    _00FC,
+    _00FD,
+    _00FE,
+    _00FF,
     _1nnn(usize),
     _2nnn(usize),
     _3xkk(u8, u8),
     _4xkk(u8, u8),
     _5xy0(u8, u8),
+    _5xy2(u8, u8),
+    _5xy3(u8, u8),
     _6xkk(u8, u8),
     _7xkk(u8, u8),
     _8xy0(u8, u8),
@@ -15,9 +26,9 @@ pub enum Instruction {
     _8xy3(u8, u8),
     _8xy4(u8, u8),
     _8xy5(u8, u8),
-    _8xy6(u8),
+    _8xy6(u8, u8),
     _8xy7(u8, u8),
-    _8xyE(u8),
+    _8xyE(u8, u8),
     _9xy0(u8, u8),
     _Annn(usize),
     _Bnnn(usize),
@@ -25,20 +36,162 @@ pub enum Instruction {
     _Dxyn(u8, u8, u8),
     _Ex9E(u8),
     _ExA1(u8),
+    _F000(usize),
+    _Fn01(u8),
+    _F002,
     _Fx07(u8),
     _Fx0A(u8),
     _Fx15(u8),
     _Fx18(u8),
     _Fx1E(u8),
     _Fx29(u8),
+    _Fx30(u8),
     _Fx33(u8),
+    _Fx3A(u8),
     _Fx55(u8),
     _Fx65(u8),
+    _Fx75(u8),
+    _Fx85(u8),
+}
+
+impl Instruction {
+    /// The number of bytes the instruction takes up.
+    pub fn size(&self) -> usize {
+        match self {
+            Instruction::_F000(_) => 4,
+            _ => 2,
+        }
+    }
+
+    /// Encodes the instruction as the bytes it decodes from.
+    pub fn encode(&self) -> Vec<u8> {
+        let xkk = |op: u16, x: u8, kk: u8| op << 12 | (x as u16 & 0xF) << 8 | kk as u16;
+        let xyn = |op: u16, x: u8, y: u8, n: u8| xkk(op, x, (y & 0xF) << 4 | (n & 0xF));
+        let nnn = |op: u16, addr: usize| op << 12 | (addr & 0xFFF) as u16;
+        let opcode = match *self {
+            Instruction::_00Cn(n) => 0x00C0 | (n as u16 & 0xF),
+            Instruction::_00E0 => 0x00E0,
+            Instruction::_00EE => 0x00EE,
+            Instruction::_00FB => 0x00FB,
+            Instruction::_00FC => 0x00FC,
+            Instruction::_00FD => 0x00FD,
+            Instruction::_00FE => 0x00FE,
+            Instruction::_00FF => 0x00FF,
+            Instruction::_1nnn(addr) => nnn(0x1, addr),
+            Instruction::_2nnn(addr) => nnn(0x2, addr),
+            Instruction::_3xkk(x, kk) => xkk(0x3, x, kk),
+            Instruction::_4xkk(x, kk) => xkk(0x4, x, kk),
+            Instruction::_5xy0(x, y) => xyn(0x5, x, y, 0x0),
+            Instruction::_5xy2(x, y) => xyn(0x5, x, y, 0x2),
+            Instruction::_5xy3(x, y) => xyn(0x5, x, y, 0x3),
+            Instruction::_6xkk(x, kk) => xkk(0x6, x, kk),
+            Instruction::_7xkk(x, kk) => xkk(0x7, x, kk),
+            Instruction::_8xy0(x, y) => xyn(0x8, x, y, 0x0),
+            Instruction::_8xy1(x, y) => xyn(0x8, x, y, 0x1),
+            Instruction::_8xy2(x, y) => xyn(0x8, x, y, 0x2),
+            Instruction::_8xy3(x, y) => xyn(0x8, x, y, 0x3),
+            Instruction::_8xy4(x, y) => xyn(0x8, x, y, 0x4),
+            Instruction::_8xy5(x, y) => xyn(0x8, x, y, 0x5),
+            Instruction::_8xy6(x, y) => xyn(0x8, x, y, 0x6),
+            Instruction::_8xy7(x, y) => xyn(0x8, x, y, 0x7),
+            Instruction::_8xyE(x, y) => xyn(0x8, x, y, 0xE),
+            Instruction::_9xy0(x, y) => xyn(0x9, x, y, 0x0),
+            Instruction::_Annn(addr) => nnn(0xA, addr),
+            Instruction::_Bnnn(addr) => nnn(0xB, addr),
+            Instruction::_Cxkk(x, kk) => xkk(0xC, x, kk),
+            Instruction::_Dxyn(x, y, n) => xyn(0xD, x, y, n),
+            Instruction::_Ex9E(x) => xkk(0xE, x, 0x9E),
+            Instruction::_ExA1(x) => xkk(0xE, x, 0xA1),
+            Instruction::_F000(addr) => return vec![0xF0, 0x00, (addr >> 8) as u8, addr as u8],
+            Instruction::_Fn01(n) => xkk(0xF, n, 0x01),
+            Instruction::_F002 => 0xF002,
+            Instruction::_Fx07(x) => xkk(0xF, x, 0x07),
+            Instruction::_Fx0A(x) => xkk(0xF, x, 0x0A),
+            Instruction::_Fx15(x) => xkk(0xF, x, 0x15),
+            Instruction::_Fx18(x) => xkk(0xF, x, 0x18),
+            Instruction::_Fx1E(x) => xkk(0xF, x, 0x1E),
+            Instruction::_Fx29(x) => xkk(0xF, x, 0x29),
+            Instruction::_Fx30(x) => xkk(0xF, x, 0x30),
+            Instruction::_Fx33(x) => xkk(0xF, x, 0x33),
+            Instruction::_Fx3A(x) => xkk(0xF, x, 0x3A),
+            Instruction::_Fx55(x) => xkk(0xF, x, 0x55),
+            Instruction::_Fx65(x) => xkk(0xF, x, 0x65),
+            Instruction::_Fx75(x) => xkk(0xF, x, 0x75),
+            Instruction::_Fx85(x) => xkk(0xF, x, 0x85),
+        };
+        opcode.to_be_bytes().to_vec()
+    }
+}
+
+/// Formats the instruction as an assembly mnemonic, e.g. `LD V1, 0xAB` or `DRW V0, V1, 5`.
+/// Addresses and bytes are written in hex, nibbles in decimal.
+impl Display for Instruction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            Instruction::_00Cn(n) => write!(f, "SCD {}", n),
+            Instruction::_00E0 => write!(f, "CLS"),
+            Instruction::_00EE => write!(f, "RET"),
+            Instruction::_00FB => write!(f, "SCR"),
+            Instruction::_00FC => write!(f, "SCL"),
+            Instruction::_00FD => write!(f, "EXIT"),
+            Instruction::_00FE => write!(f, "LOW"),
+            Instruction::_00FF => write!(f, "HIGH"),
+            Instruction::_1nnn(addr) => write!(f, "JP {:#05X}", addr),
+            Instruction::_2nnn(addr) => write!(f, "CALL {:#05X}", addr),
+            Instruction::_3xkk(x, kk) => write!(f, "SE V{:X}, {:#04X}", x, kk),
+            Instruction::_4xkk(x, kk) => write!(f, "SNE V{:X}, {:#04X}", x, kk),
+            Instruction::_5xy0(x, y) => write!(f, "SE V{:X}, V{:X}", x, y),
+            Instruction::_5xy2(x, y) => write!(f, "SAVE V{:X}, V{:X}", x, y),
+            Instruction::_5xy3(x, y) => write!(f, "LOAD V{:X}, V{:X}", x, y),
+            Instruction::_6xkk(x, kk) => write!(f, "LD V{:X}, {:#04X}", x, kk),
+            Instruction::_7xkk(x, kk) => write!(f, "ADD V{:X}, {:#04X}", x, kk),
+            Instruction::_8xy0(x, y) => write!(f, "LD V{:X}, V{:X}", x, y),
+            Instruction::_8xy1(x, y) => write!(f, "OR V{:X}, V{:X}", x, y),
+            Instruction::_8xy2(x, y) => write!(f, "AND V{:X}, V{:X}", x, y),
+            Instruction::_8xy3(x, y) => write!(f, "XOR V{:X}, V{:X}", x, y),
+            Instruction::_8xy4(x, y) => write!(f, "ADD V{:X}, V{:X}", x, y),
+            Instruction::_8xy5(x, y) => write!(f, "SUB V{:X}, V{:X}", x, y),
+            Instruction::_8xy6(x, y) => write!(f, "SHR V{:X}, V{:X}", x, y),
+            Instruction::_8xy7(x, y) => write!(f, "SUBN V{:X}, V{:X}", x, y),
+            Instruction::_8xyE(x, y) => write!(f, "SHL V{:X}, V{:X}", x, y),
+            Instruction::_9xy0(x, y) => write!(f, "SNE V{:X}, V{:X}", x, y),
+            Instruction::_Annn(addr) => write!(f, "LD I, {:#05X}", addr),
+            Instruction::_Bnnn(addr) => write!(f, "JP V0, {:#05X}", addr),
+            Instruction::_Cxkk(x, kk) => write!(f, "RND V{:X}, {:#04X}", x, kk),
+            Instruction::_Dxyn(x, y, n) => write!(f, "DRW V{:X}, V{:X}, {}", x, y, n),
+            Instruction::_Ex9E(x) => write!(f, "SKP V{:X}", x),
+            Instruction::_ExA1(x) => write!(f, "SKNP V{:X}", x),
+            Instruction::_F000(addr) => write!(f, "LD I, LONG {:#06X}", addr),
+            Instruction::_Fn01(n) => write!(f, "PLANE {}", n),
+            Instruction::_F002 => write!(f, "AUDIO"),
+            Instruction::_Fx07(x) => write!(f, "LD V{:X}, DT", x),
+            Instruction::_Fx0A(x) => write!(f, "LD V{:X}, K", x),
+            Instruction::_Fx15(x) => write!(f, "LD DT, V{:X}", x),
+            Instruction::_Fx18(x) => write!(f, "LD ST, V{:X}", x),
+            Instruction::_Fx1E(x) => write!(f, "ADD I, V{:X}", x),
+            Instruction::_Fx29(x) => write!(f, "LD F, V{:X}", x),
+            Instruction::_Fx30(x) => write!(f, "LD HF, V{:X}", x),
+            Instruction::_Fx33(x) => write!(f, "LD B, V{:X}", x),
+            Instruction::_Fx3A(x) => write!(f, "PITCH V{:X}", x),
+            Instruction::_Fx55(x) => write!(f, "LD [I], V{:X}", x),
+            Instruction::_Fx65(x) => write!(f, "LD V{:X}, [I]", x),
+            Instruction::_Fx75(x) => write!(f, "LD R, V{:X}", x),
+            Instruction::_Fx85(x) => write!(f, "LD V{:X}, R", x),
+        }
+    }
 }
 
-impl From<&[u8]> for Instruction {
-    fn from(bytes: &[u8]) -> Self {
-        assert_eq!(bytes.len(), 2);
+/// Decodes the instruction at the start of `bytes`. All instructions are 2 bytes long, except for
+/// the 4 byte XO-CHIP F000 NNNN, which is only decoded if `bytes` holds the full 4 bytes. Input
+/// shorter than 2 bytes fails with the available byte as the high byte of the opcode.
+impl TryFrom<&[u8]> for Instruction {
+    /// The opcode that could not be decoded.
+    type Error = u16;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        if bytes.len() < 2 {
+            return Err(bytes.first().map_or(0, |&byte| (byte as u16) << 8));
+        }
         let op_type = bytes[0] >> 4;
         let x = bytes[0] & 0x0F;
 
@@ -49,14 +202,22 @@ impl From<&[u8]> for Instruction {
         let combined: usize = (bytes[0] as usize) << 8 | bytes[1] as usize;
         let nnn = combined & 0x0FFF;
 
-        match (op_type, x, y, n) {
+        let instruction = match (op_type, x, y, n) {
+            (0x0, 0x0, 0xC, _) => Instruction::_00Cn(n),
             (0x0, 0x0, 0xE, 0x0) => Instruction::_00E0,
             (0x0, 0x0, 0xE, 0xE) => Instruction::_00EE,
+            (0x0, 0x0, 0xF, 0xB) => Instruction::_00FB,
+            (0x0, 0x0, 0xF, 0xC) => Instruction::_00FC,
+            (0x0, 0x0, 0xF, 0xD) => Instruction::_00FD,
+            (0x0, 0x0, 0xF, 0xE) => Instruction::_00FE,
+            (0x0, 0x0, 0xF, 0xF) => Instruction::_00FF,
             (0x1, _, _, _) => Instruction::_1nnn(nnn),
             (0x2, _, _, _) => Instruction::_2nnn(nnn),
             (0x3, _, _, _) => Instruction::_3xkk(x, kk),
             (0x4, _, _, _) => Instruction::_4xkk(x, kk),
-            (0x5, _, _, _) => Instruction::_5xy0(x, y),
+            (0x5, _, _, 0x0) => Instruction::_5xy0(x, y),
+            (0x5, _, _, 0x2) => Instruction::_5xy2(x, y),
+            (0x5, _, _, 0x3) => Instruction::_5xy3(x, y),
             (0x6, _, _, _) => Instruction::_6xkk(x, kk),
             (0x7, _, _, _) => Instruction::_7xkk(x, kk),
             (0x8, _, _, 0x0) => Instruction::_8xy0(x, y),
@@ -65,9 +226,9 @@ impl From<&[u8]> for Instruction {
             (0x8, _, _, 0x3) => Instruction::_8xy3(x, y),
             (0x8, _, _, 0x4) => Instruction::_8xy4(x, y),
             (0x8, _, _, 0x5) => Instruction::_8xy5(x, y),
-            (0x8, _, _, 0x6) => Instruction::_8xy6(x),
+            (0x8, _, _, 0x6) => Instruction::_8xy6(x, y),
             (0x8, _, _, 0x7) => Instruction::_8xy7(x, y),
-            (0x8, _, _, 0xE) => Instruction::_8xyE(x),
+            (0x8, _, _, 0xE) => Instruction::_8xyE(x, y),
             (0x9, _, _, 0x0) => Instruction::_9xy0(x, y),
             (0xA, _, _, _) => Instruction::_Annn(nnn),
             (0xB, _, _, _) => Instruction::_Bnnn(nnn),
@@ -75,16 +236,94 @@ impl From<&[u8]> for Instruction {
             (0xD, _, _, _) => Instruction::_Dxyn(x, y, n),
             (0xE, _, 0x9, 0xE) => Instruction::_Ex9E(x),
             (0xE, _, 0xA, 0x1) => Instruction::_ExA1(x),
+            (0xF, 0x0, 0x0, 0x0) if bytes.len() >= 4 => {
+                Instruction::_F000((bytes[2] as usize) << 8 | bytes[3] as usize)
+            }
+            (0xF, _, 0x0, 0x1) => Instruction::_Fn01(x),
+            (0xF, 0x0, 0x0, 0x2) => Instruction::_F002,
             (0xF, _, 0x0, 0x7) => Instruction::_Fx07(x),
             (0xF, _, 0x0, 0xA) => Instruction::_Fx0A(x),
             (0xF, _, 0x1, 0x5) => Instruction::_Fx15(x),
             (0xF, _, 0x1, 0x8) => Instruction::_Fx18(x),
             (0xF, _, 0x1, 0xE) => Instruction::_Fx1E(x),
             (0xF, _, 0x2, 0x9) => Instruction::_Fx29(x),
+            (0xF, _, 0x3, 0x0) => Instruction::_Fx30(x),
             (0xF, _, 0x3, 0x3) => Instruction::_Fx33(x),
+            (0xF, _, 0x3, 0xA) => Instruction::_Fx3A(x),
             (0xF, _, 0x5, 0x5) => Instruction::_Fx55(x),
             (0xF, _, 0x6, 0x5) => Instruction::_Fx65(x),
-            _ => unreachable!(),
+            (0xF, _, 0x7, 0x5) => Instruction::_Fx75(x),
+            (0xF, _, 0x8, 0x5) => Instruction::_Fx85(x),
+            _ => return Err(combined as u16),
+        };
+        Ok(instruction)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn decode() {
+        let instruction = Instruction::try_from(&[0xD1, 0x25][..]);
+        assert!(matches!(instruction, Ok(Instruction::_Dxyn(0x1, 0x2, 0x5))));
+    }
+
+    #[test]
+    fn decode_schip() {
+        let instruction = Instruction::try_from(&[0x00, 0xC4][..]);
+        assert!(matches!(instruction, Ok(Instruction::_00Cn(0x4))));
+
+        let instruction = Instruction::try_from(&[0x00, 0xFF][..]);
+        assert!(matches!(instruction, Ok(Instruction::_00FF)));
+
+        let instruction = Instruction::try_from(&[0xF3, 0x75][..]);
+        assert!(matches!(instruction, Ok(Instruction::_Fx75(0x3))));
+    }
+
+    #[test]
+    fn decode_long_load() {
+        let instruction = Instruction::try_from(&[0xF0, 0x00, 0x12, 0x34][..]);
+        assert!(matches!(instruction, Ok(Instruction::_F000(0x1234))));
+
+        let instruction = Instruction::try_from(&[0xF0, 0x00][..]);
+        assert!(matches!(instruction, Err(0xF000)));
+    }
+
+    #[test]
+    fn display() {
+        let mnemonic = |bytes: &[u8]| Instruction::try_from(bytes).unwrap().to_string();
+        assert_eq!(mnemonic(&[0x61, 0xAB]), "LD V1, 0xAB");
+        assert_eq!(mnemonic(&[0xD0, 0x15]), "DRW V0, V1, 5");
+        assert_eq!(mnemonic(&[0x12, 0x0A]), "JP 0x20A");
+        assert_eq!(mnemonic(&[0xB3, 0x00]), "JP V0, 0x300");
+        assert_eq!(mnemonic(&[0xFE, 0x55]), "LD [I], VE");
+        assert_eq!(mnemonic(&[0x00, 0xC4]), "SCD 4");
+        assert_eq!(mnemonic(&[0xF0, 0x00, 0x12, 0x34]), "LD I, LONG 0x1234");
+    }
+
+    #[test]
+    fn encode() {
+        for opcode in 0..=0xFFFFu16 {
+            let bytes = opcode.to_be_bytes();
+            if let Ok(instruction) = Instruction::try_from(&bytes[..]) {
+                assert_eq!(instruction.encode(), bytes, "{:04X}", opcode);
+            }
         }
+        let bytes = [0xF0, 0x00, 0x12, 0x34];
+        assert_eq!(Instruction::try_from(&bytes[..]).unwrap().encode(), bytes);
+    }
+
+    #[test]
+    fn decode_invalid() {
+        let instruction = Instruction::try_from(&[0xE1, 0x00][..]);
+        assert!(matches!(instruction, Err(0xE100)));
+    }
+
+    #[test]
+    fn decode_short() {
+        assert!(matches!(Instruction::try_from(&[0x12][..]), Err(0x1200)));
+        assert!(matches!(Instruction::try_from(&[][..]), Err(0)));
     }
 }
@@ -1,5 +1,8 @@
 pub mod chip8;
 pub mod display_state;
+pub mod error;
 pub mod instruction;
+pub mod platform;
+pub mod quirks;
 pub mod ram;
 pub mod registers;
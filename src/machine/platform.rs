@@ -0,0 +1,69 @@
+use std::str::FromStr;
+
+use super::chip8::DEFAULT_IPF;
+use super::quirks::Quirks;
+
+/// A Chip-8 variant, bundling the quirks and speed its programs are usually written for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Platform {
+    /// The original interpreter on the COSMAC VIP.
+    Chip8,
+    /// SUPER-CHIP 1.1 on the HP 48.
+    SuperChip,
+    /// XO-CHIP, as implemented by Octo.
+    XoChip,
+}
+
+impl Platform {
+    /// The names accepted by `Platform::from_str`.
+    pub const PRESETS: [&'static str; 3] = ["chip8", "schip", "xochip"];
+
+    pub fn quirks(self) -> Quirks {
+        match self {
+            Platform::Chip8 => Quirks::COSMAC_VIP,
+            Platform::SuperChip => Quirks::SUPER_CHIP,
+            Platform::XoChip => Quirks::MODERN,
+        }
+    }
+
+    /// Instructions per frame.
+    pub fn ipf(self) -> usize {
+        match self {
+            Platform::Chip8 => DEFAULT_IPF,
+            Platform::SuperChip => 30,
+            Platform::XoChip => 200,
+        }
+    }
+}
+
+impl FromStr for Platform {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "chip8" => Ok(Platform::Chip8),
+            "schip" | "superchip" => Ok(Platform::SuperChip),
+            "xochip" => Ok(Platform::XoChip),
+            _ => Err(format!(
+                "unknown platform '{}', expected one of: {}",
+                s,
+                Platform::PRESETS.join(", ")
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn from_str() {
+        assert_eq!("XOCHIP".parse::<Platform>(), Ok(Platform::XoChip));
+        assert!("chip9".parse::<Platform>().is_err());
+        for preset in Platform::PRESETS {
+            assert!(preset.parse::<Platform>().is_ok());
+        }
+        assert_eq!(Platform::SuperChip.quirks(), Quirks::SUPER_CHIP);
+    }
+}
@@ -0,0 +1,124 @@
+use std::str::FromStr;
+
+/// Behaviour of the opcodes that were interpreted differently across Chip-8 implementations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Quirks {
+    /// 8xy6/8xyE shift Vy into Vx, rather than shifting Vx in place.
+    pub shift_uses_vy: bool,
+    /// Fx55/Fx65 leave I pointing past the last register stored or loaded.
+    pub load_store_increments_i: bool,
+    /// With load_store_increments_i, I is left on the last register instead of past it.
+    pub load_store_stops_short: bool,
+    /// Bnnn jumps to nnn + Vx (where x is the high nibble of nnn), rather than nnn + V0.
+    pub jump_uses_vx: bool,
+    /// 8xy1/8xy2/8xy3 reset VF to 0.
+    pub vf_reset: bool,
+    /// Sprites are clipped at the edges of the screen, rather than wrapping around.
+    pub clipping: bool,
+    /// Dxyn waits for the next frame if a sprite was already drawn in the current one.
+    pub display_wait: bool,
+    /// 64 KB of memory, as on XO-CHIP, rather than the original 4 KB.
+    pub extended_memory: bool,
+}
+
+impl Quirks {
+    /// The original COSMAC VIP interpreter.
+    pub const COSMAC_VIP: Quirks = Quirks {
+        shift_uses_vy: true,
+        load_store_increments_i: true,
+        load_store_stops_short: false,
+        jump_uses_vx: false,
+        vf_reset: true,
+        clipping: true,
+        display_wait: true,
+        extended_memory: false,
+    };
+
+    /// CHIP-48 on the HP-48 calculators.
+    pub const CHIP_48: Quirks = Quirks {
+        shift_uses_vy: false,
+        load_store_increments_i: true,
+        load_store_stops_short: true,
+        jump_uses_vx: true,
+        vf_reset: false,
+        clipping: true,
+        display_wait: false,
+        extended_memory: false,
+    };
+
+    /// SUPER-CHIP 1.1.
+    pub const SUPER_CHIP: Quirks = Quirks {
+        shift_uses_vy: false,
+        load_store_increments_i: false,
+        load_store_stops_short: false,
+        jump_uses_vx: true,
+        vf_reset: false,
+        clipping: true,
+        display_wait: false,
+        extended_memory: false,
+    };
+
+    /// What most modern interpreters (such as Octo) do.
+    pub const MODERN: Quirks = Quirks {
+        shift_uses_vy: true,
+        load_store_increments_i: true,
+        load_store_stops_short: false,
+        jump_uses_vx: false,
+        vf_reset: false,
+        clipping: false,
+        display_wait: false,
+        extended_memory: true,
+    };
+
+    /// The names accepted by `Quirks::from_str`.
+    pub const PRESETS: [&'static str; 5] = ["default", "vip", "chip48", "schip", "modern"];
+}
+
+impl Default for Quirks {
+    fn default() -> Self {
+        Quirks {
+            shift_uses_vy: false,
+            load_store_increments_i: false,
+            load_store_stops_short: false,
+            jump_uses_vx: false,
+            vf_reset: false,
+            clipping: true,
+            display_wait: false,
+            extended_memory: false,
+        }
+    }
+}
+
+impl FromStr for Quirks {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "default" => Ok(Quirks::default()),
+            "vip" | "cosmac" => Ok(Quirks::COSMAC_VIP),
+            "chip48" => Ok(Quirks::CHIP_48),
+            "schip" | "superchip" => Ok(Quirks::SUPER_CHIP),
+            "modern" => Ok(Quirks::MODERN),
+            _ => Err(format!(
+                "unknown quirks preset '{}', expected one of: {}",
+                s,
+                Quirks::PRESETS.join(", ")
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn from_str() {
+        assert_eq!("VIP".parse::<Quirks>(), Ok(Quirks::COSMAC_VIP));
+        assert_eq!("modern".parse::<Quirks>(), Ok(Quirks::MODERN));
+        assert!("cosmic".parse::<Quirks>().is_err());
+        for preset in Quirks::PRESETS {
+            assert!(preset.parse::<Quirks>().is_ok());
+        }
+    }
+}
@@ -1,36 +1,105 @@
+use crate::machine::error::Chip8Error;
 use std::fmt::{Debug, Formatter};
 
-pub const MAX_SIZE: usize = 4096;
+// the 4 KB of the original Chip-8 and SUPER-CHIP
+pub const CHIP8_SIZE: usize = 0x1000;
+// XO-CHIP extends the original 4 KB address space to the full 16-bit range
+pub const MAX_SIZE: usize = 0x10000;
 
+/// The address space of the machine: 4 KB, or 64 KB for XO-CHIP.
 pub struct Ram {
-    memory: [u8; MAX_SIZE],
+    memory: Vec<u8>,
 }
 
 impl Ram {
     pub fn initialise() -> Ram {
+        Ram::with_size(CHIP8_SIZE)
+    }
+
+    pub fn with_size(size: usize) -> Ram {
         Ram {
-            memory: [0x00; MAX_SIZE],
+            memory: vec![0x00; size],
         }
     }
 
-    pub fn read_bytes(&self, offset: usize, bytes: usize) -> &[u8] {
-        &self.memory[offset..offset + bytes]
+    /// The number of bytes of memory.
+    pub fn size(&self) -> usize {
+        self.memory.len()
+    }
+
+    pub fn read_bytes(&self, offset: usize, bytes: usize) -> Result<&[u8], Chip8Error> {
+        offset
+            .checked_add(bytes)
+            .and_then(|end| self.memory.get(offset..end))
+            .ok_or(Chip8Error::MemoryOutOfBounds {
+                addr: offset,
+                len: bytes,
+            })
     }
 
-    pub fn write_bytes(&mut self, offset: usize, bytes: &[u8]) {
-        self.memory[offset..offset + bytes.len()].copy_from_slice(bytes)
+    pub fn write_bytes(&mut self, offset: usize, bytes: &[u8]) -> Result<(), Chip8Error> {
+        offset
+            .checked_add(bytes.len())
+            .and_then(|end| self.memory.get_mut(offset..end))
+            .ok_or(Chip8Error::MemoryOutOfBounds {
+                addr: offset,
+                len: bytes.len(),
+            })?
+            .copy_from_slice(bytes);
+        Ok(())
     }
 }
 
 impl Debug for Ram {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "Mem: ").unwrap();
+        write!(f, "Mem: ")?;
         for (offset, byte) in self.memory.iter().enumerate() {
             if offset % 32 == 0 {
-                writeln!(f).unwrap();
+                writeln!(f)?;
             }
-            write!(f, "{:#04X} ", byte).unwrap();
+            write!(f, "{:#04X} ", byte)?;
         }
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn read_write() {
+        let mut ram = Ram::initialise();
+        ram.write_bytes(0x200, &[0xAB, 0xCD]).unwrap();
+        assert_eq!(ram.read_bytes(0x200, 2).unwrap(), &[0xAB, 0xCD]);
+    }
+
+    #[test]
+    fn out_of_bounds() {
+        let ram = Ram::initialise();
+        assert!(matches!(
+            ram.read_bytes(CHIP8_SIZE - 1, 2),
+            Err(Chip8Error::MemoryOutOfBounds {
+                addr: 0xFFF,
+                len: 2
+            })
+        ));
+        let mut ram = Ram::with_size(MAX_SIZE);
+        assert!(ram.read_bytes(CHIP8_SIZE, 2).is_ok());
+        assert!(matches!(
+            ram.write_bytes(MAX_SIZE, &[0x00]),
+            Err(Chip8Error::MemoryOutOfBounds {
+                addr: 0x10000,
+                len: 1
+            })
+        ));
+        assert!(matches!(
+            ram.read_bytes(usize::MAX, 1),
+            Err(Chip8Error::MemoryOutOfBounds { .. })
+        ));
+        assert!(matches!(
+            ram.write_bytes(usize::MAX, &[0x00, 0x00]),
+            Err(Chip8Error::MemoryOutOfBounds { .. })
+        ));
+    }
+}
@@ -1,5 +1,6 @@
 use std::fmt::{Debug, Formatter};
 
+/// The general purpose registers V0 - VF, the address register I and the two timers.
 pub struct Registers {
     vx: [u8; 16],
     i: usize,
@@ -50,13 +51,19 @@ impl Registers {
     }
 }
 
+impl Default for Registers {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Debug for Registers {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         for (register, value) in self.vx.iter().enumerate() {
-            writeln!(f, "V{:X}: {:#04X}", register, value).unwrap();
+            writeln!(f, "V{:X}: {:#04X}", register, value)?;
         }
-        writeln!(f, "I: {:#06X} ", self.i).unwrap();
-        writeln!(f, "delay: {:#04X}", self.delay).unwrap();
+        writeln!(f, "I: {:#06X} ", self.i)?;
+        writeln!(f, "delay: {:#04X}", self.delay)?;
         writeln!(f, "sound: {:#04X}", self.sound)
     }
 }
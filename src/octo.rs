@@ -0,0 +1,962 @@
+//! Compiles programs written in [Octo](https://github.com/JohnEarnest/Octo), the assembly
+//! language most Chip-8 programs are written in nowadays.
+//!
+//! Supported are labels, `:alias`, `:const`, `:macro`, `:byte` and `:org`, every instruction
+//! including the SUPER-CHIP and XO-CHIP ones, `if ... then`, `if ... begin ... else ... end`,
+//! `loop ... while ... again`, and numbers on their own, which are written out as bytes for
+//! sprites and other data. `:calc`, `:unpack`, `:next` and the other compile-time features are not.
+//!
+//! ```
+//! use nibble8::octo;
+//!
+//! let rom = octo::compile(
+//!     "
+//!     : main
+//!         v0 := 0
+//!         loop
+//!             v0 += 1
+//!             if v0 != 10 then
+//!         again
+//!     ",
+//!     0x200,
+//! )?;
+//! assert_eq!(rom, [0x60, 0x00, 0x70, 0x01, 0x30, 0x0A, 0x12, 0x02]);
+//! # Ok::<(), nibble8::assembler::AssembleError>(())
+//! ```
+
+use std::collections::HashMap;
+
+use crate::assembler::AssembleError;
+use crate::machine::instruction::Instruction;
+use crate::machine::ram::MAX_SIZE;
+
+// how many macros may be expanded, to stop macros that expand themselves
+const MAX_EXPANSIONS: usize = 10_000;
+
+#[derive(Debug, Clone, Copy)]
+struct Token<'a> {
+    text: &'a str,
+    line: usize,
+}
+
+struct Macro<'a> {
+    params: Vec<&'a str>,
+    body: Vec<Token<'a>>,
+}
+
+// An address that is filled in once the label it refers to is defined.
+struct Fixup<'a> {
+    address: usize,
+    label: &'a str,
+    line: usize,
+    // whether the label goes in the 16 bits after an `i := long`, rather than in the low 12
+    // bits of the instruction
+    long: bool,
+}
+
+// A block that is still open, with the line it was opened on.
+enum Block {
+    // `if ... begin`, with the address of the jump past the block
+    If {
+        jump: usize,
+        line: usize,
+    },
+    // `else`, with the address of the jump past the else block
+    Else {
+        jump: usize,
+        line: usize,
+    },
+    // `loop`, with where it starts and the jumps out of it made by `while`
+    Loop {
+        start: usize,
+        exits: Vec<usize>,
+        line: usize,
+    },
+}
+
+// The instructions that test a condition: some that set it up, then one that skips the next
+// instruction if the condition holds and one that skips it if it does not.
+struct Condition {
+    setup: Vec<Instruction>,
+    skip_if_true: Instruction,
+    skip_if_false: Instruction,
+}
+
+// A register or a value, as on either side of a comparison.
+enum Operand {
+    Register(u8),
+    Value(u8),
+}
+
+/// Compiles Octo `source` into a program to be loaded at `origin`.
+///
+/// If the program has a `main` label anywhere but at its start, it begins with a jump to `main`,
+/// as Octo programs do. Stops at the first error, which says on which line it is.
+pub fn compile(source: &str, origin: usize) -> Result<Vec<u8>, AssembleError> {
+    let mut tokens: Vec<Token> = source
+        .lines()
+        .enumerate()
+        .flat_map(|(i, text)| {
+            let text = text.split('#').next().unwrap_or("");
+            text.split_whitespace()
+                .map(move |text| Token { text, line: i + 1 })
+        })
+        .collect();
+    let has_main = tokens
+        .windows(2)
+        .any(|pair| pair[0].text == ":" && pair[1].text == "main");
+    // tokens are taken from the end
+    tokens.reverse();
+
+    let mut compiler = Compiler {
+        origin,
+        tokens,
+        line: 1,
+        rom: vec![],
+        here: origin,
+        started: !has_main,
+        labels: HashMap::new(),
+        constants: HashMap::new(),
+        aliases: HashMap::new(),
+        macros: HashMap::new(),
+        expansions: 0,
+        fixups: vec![],
+        blocks: vec![],
+    };
+    while !compiler.tokens.is_empty() {
+        compiler.statement().map_err(|message| AssembleError {
+            line: compiler.line,
+            message,
+        })?;
+    }
+    compiler.finish()
+}
+
+struct Compiler<'a> {
+    origin: usize,
+    // what is left to compile, in reverse
+    tokens: Vec<Token<'a>>,
+    // the line of the last token taken
+    line: usize,
+    rom: Vec<u8>,
+    // the address the next byte goes to
+    here: usize,
+    // whether anything was compiled yet, or the program has no main to jump to
+    started: bool,
+    labels: HashMap<&'a str, usize>,
+    constants: HashMap<&'a str, i64>,
+    aliases: HashMap<&'a str, u8>,
+    macros: HashMap<&'a str, Macro<'a>>,
+    expansions: usize,
+    fixups: Vec<Fixup<'a>>,
+    blocks: Vec<Block>,
+}
+
+impl<'a> Compiler<'a> {
+    fn next(&mut self) -> Result<&'a str, String> {
+        let token = self.tokens.pop().ok_or("unexpected end of program")?;
+        self.line = token.line;
+        Ok(token.text)
+    }
+
+    fn peek(&self) -> Option<&'a str> {
+        self.tokens.last().map(|token| token.text)
+    }
+
+    fn expect(&mut self, expected: &str) -> Result<(), String> {
+        match self.next()? {
+            token if token == expected => Ok(()),
+            token => Err(format!("expected `{}`, got `{}`", expected, token)),
+        }
+    }
+
+    fn statement(&mut self) -> Result<(), String> {
+        let token = self.next()?;
+        match token {
+            ":" => {
+                let name = self.name()?;
+                self.start(name == "main")?;
+                if self.labels.insert(name, self.here).is_some() {
+                    return Err(format!("label `{}` is already defined", name));
+                }
+            }
+            ":alias" => {
+                let name = self.name()?;
+                let register = self.register()?;
+                self.aliases.insert(name, register);
+            }
+            ":const" => {
+                let name = self.name()?;
+                let value = self.value()?;
+                self.constants.insert(name, value);
+            }
+            ":macro" => self.define_macro()?,
+            ":byte" => {
+                let byte = self.byte()?;
+                self.emit(&[byte])?;
+            }
+            ":org" => {
+                let address = self.value()?;
+                self.start(false)?;
+                let here = usize::try_from(address)
+                    .ok()
+                    .filter(|&address| address < MAX_SIZE)
+                    .ok_or_else(|| {
+                        format!("cannot compile to {:#X}, past the end of memory", address)
+                    })?;
+                if here < self.origin {
+                    return Err(format!("cannot compile to {:#X}, before the program", here));
+                }
+                self.here = here;
+            }
+            ":breakpoint" | ":monitor" => {
+                // only meaningful in the Octo IDE
+                self.next()?;
+            }
+            ";" | "return" => self.instruction(Instruction::_00EE)?,
+            "clear" => self.instruction(Instruction::_00E0)?,
+            "hires" => self.instruction(Instruction::_00FF)?,
+            "lores" => self.instruction(Instruction::_00FE)?,
+            "exit" => self.instruction(Instruction::_00FD)?,
+            "scroll-right" => self.instruction(Instruction::_00FB)?,
+            "scroll-left" => self.instruction(Instruction::_00FC)?,
+            "audio" => self.instruction(Instruction::_F002)?,
+            "scroll-down" => {
+                let n = self.nibble()?;
+                self.instruction(Instruction::_00Cn(n))?
+            }
+            "plane" => {
+                let n = self.nibble()?;
+                self.instruction(Instruction::_Fn01(n))?
+            }
+            "bcd" => {
+                let x = self.register()?;
+                self.instruction(Instruction::_Fx33(x))?
+            }
+            "save" | "load" => {
+                let x = self.register()?;
+                let instruction = if self.peek() == Some("-") {
+                    self.next()?;
+                    let y = self.register()?;
+                    if token == "save" {
+                        Instruction::_5xy2(x, y)
+                    } else {
+                        Instruction::_5xy3(x, y)
+                    }
+                } else if token == "save" {
+                    Instruction::_Fx55(x)
+                } else {
+                    Instruction::_Fx65(x)
+                };
+                self.instruction(instruction)?
+            }
+            "saveflags" => {
+                let x = self.register()?;
+                self.instruction(Instruction::_Fx75(x))?
+            }
+            "loadflags" => {
+                let x = self.register()?;
+                self.instruction(Instruction::_Fx85(x))?
+            }
+            "sprite" => {
+                let (x, y) = (self.register()?, self.register()?);
+                let n = self.nibble()?;
+                self.instruction(Instruction::_Dxyn(x, y, n))?
+            }
+            "jump" => self.address(Instruction::_1nnn)?,
+            "jump0" => self.address(Instruction::_Bnnn)?,
+            "i" => self.assign_i()?,
+            "delay" | "buzzer" | "pitch" => {
+                self.expect(":=")?;
+                let x = self.register()?;
+                self.instruction(match token {
+                    "delay" => Instruction::_Fx15(x),
+                    "buzzer" => Instruction::_Fx18(x),
+                    _ => Instruction::_Fx3A(x),
+                })?
+            }
+            "if" => self.if_statement()?,
+            "else" => match self.blocks.pop() {
+                Some(Block::If { jump, line }) => {
+                    let end = self.placeholder()?;
+                    self.patch(jump, self.here)?;
+                    self.blocks.push(Block::Else { jump: end, line });
+                }
+                _ => return Err("`else` without `if ... begin`".to_string()),
+            },
+            "end" => match self.blocks.pop() {
+                Some(Block::If { jump, .. } | Block::Else { jump, .. }) => {
+                    self.patch(jump, self.here)?
+                }
+                _ => return Err("`end` without `if ... begin`".to_string()),
+            },
+            "loop" => {
+                self.start(false)?;
+                self.blocks.push(Block::Loop {
+                    start: self.here,
+                    exits: vec![],
+                    line: self.line,
+                });
+            }
+            "while" => {
+                let condition = self.condition()?;
+                self.test(condition.setup, condition.skip_if_true)?;
+                let exit = self.placeholder()?;
+                match self
+                    .blocks
+                    .iter_mut()
+                    .rev()
+                    .find(|block| matches!(block, Block::Loop { .. }))
+                {
+                    Some(Block::Loop { exits, .. }) => exits.push(exit),
+                    _ => return Err("`while` outside of a loop".to_string()),
+                }
+            }
+            "again" => match self.blocks.pop() {
+                Some(Block::Loop { start, exits, .. }) => {
+                    self.instruction(Instruction::_1nnn(start))?;
+                    for exit in exits {
+                        self.patch(exit, self.here)?;
+                    }
+                }
+                _ => return Err("`again` without `loop`".to_string()),
+            },
+            _ if self.macros.contains_key(token) => self.expand(token)?,
+            _ if self.register_named(token).is_some() => self.assign_register(token)?,
+            _ if token.starts_with(':') => {
+                return Err(format!("unsupported directive `{}`", token))
+            }
+            _ if is_number(token) => {
+                let byte = as_byte(number(token)?)?;
+                self.emit(&[byte])?;
+            }
+            _ if self.constants.contains_key(token) => {
+                let byte = as_byte(self.constants[token])?;
+                self.emit(&[byte])?;
+            }
+            // anything else is a call to a subroutine
+            _ => {
+                self.tokens.push(Token {
+                    text: token,
+                    line: self.line,
+                });
+                self.address(Instruction::_2nnn)?
+            }
+        }
+        Ok(())
+    }
+
+    fn define_macro(&mut self) -> Result<(), String> {
+        let name = self.name()?;
+        let mut params = vec![];
+        loop {
+            match self.next()? {
+                "{" => break,
+                param => params.push(param),
+            }
+        }
+        let mut body = vec![];
+        let mut depth = 1;
+        loop {
+            let token = self.tokens.pop().ok_or("`{` without `}`")?;
+            match token.text {
+                "{" => depth += 1,
+                "}" => depth -= 1,
+                _ => {}
+            }
+            if depth == 0 {
+                break;
+            }
+            body.push(token);
+        }
+        self.macros.insert(name, Macro { params, body });
+        Ok(())
+    }
+
+    // Replaces a use of a macro by its body, with its parameters replaced by the arguments.
+    fn expand(&mut self, name: &str) -> Result<(), String> {
+        self.expansions += 1;
+        if self.expansions > MAX_EXPANSIONS {
+            return Err(format!("macro `{}` expands without end", name));
+        }
+        let line = self.line;
+        let macro_ = &self.macros[name];
+        let params = macro_.params.clone();
+        let mut args = HashMap::new();
+        for param in params {
+            args.insert(param, self.next()?);
+        }
+        let macro_ = &self.macros[name];
+        let body: Vec<Token> = macro_
+            .body
+            .iter()
+            .rev()
+            .map(|token| Token {
+                text: args.get(token.text).copied().unwrap_or(token.text),
+                line,
+            })
+            .collect();
+        self.tokens.extend(body);
+        Ok(())
+    }
+
+    fn if_statement(&mut self) -> Result<(), String> {
+        let condition = self.condition()?;
+        match self.next()? {
+            "then" => self.test(condition.setup, condition.skip_if_false),
+            "begin" => {
+                self.test(condition.setup, condition.skip_if_true)?;
+                let jump = self.placeholder()?;
+                self.blocks.push(Block::If {
+                    jump,
+                    line: self.line,
+                });
+                Ok(())
+            }
+            token => Err(format!("expected `then` or `begin`, got `{}`", token)),
+        }
+    }
+
+    fn test(&mut self, setup: Vec<Instruction>, skip: Instruction) -> Result<(), String> {
+        for instruction in setup {
+            self.instruction(instruction)?;
+        }
+        self.instruction(skip)
+    }
+
+    fn condition(&mut self) -> Result<Condition, String> {
+        let x = self.register()?;
+        let operator = self.next()?;
+        let (skip_if_true, skip_if_false) = match operator {
+            "key" => (Instruction::_Ex9E(x), Instruction::_ExA1(x)),
+            "-key" => (Instruction::_ExA1(x), Instruction::_Ex9E(x)),
+            "==" | "!=" => {
+                let (equal, not_equal) = match self.operand()? {
+                    Operand::Register(y) => (Instruction::_5xy0(x, y), Instruction::_9xy0(x, y)),
+                    Operand::Value(kk) => (Instruction::_3xkk(x, kk), Instruction::_4xkk(x, kk)),
+                };
+                if operator == "==" {
+                    (equal, not_equal)
+                } else {
+                    (not_equal, equal)
+                }
+            }
+            "<" | ">" | "<=" | ">=" => {
+                let left = Operand::Register(x);
+                let right = self.operand()?;
+                // VF ends up 1 if the first operand is at least the second, 0 if it is smaller
+                let setup = if matches!(operator, "<" | ">=") {
+                    at_least(left, right)
+                } else {
+                    at_least(right, left)
+                };
+                let (zero, one) = (
+                    (Instruction::_3xkk(0xF, 0), Instruction::_4xkk(0xF, 0)),
+                    (Instruction::_3xkk(0xF, 1), Instruction::_4xkk(0xF, 1)),
+                );
+                let (skip_if_true, skip_if_false) = if matches!(operator, "<" | ">") {
+                    zero
+                } else {
+                    one
+                };
+                return Ok(Condition {
+                    setup,
+                    skip_if_true,
+                    skip_if_false,
+                });
+            }
+            _ => return Err(format!("unknown comparison `{}`", operator)),
+        };
+        Ok(Condition {
+            setup: vec![],
+            skip_if_true,
+            skip_if_false,
+        })
+    }
+
+    fn assign_i(&mut self) -> Result<(), String> {
+        match self.next()? {
+            ":=" => match self.peek() {
+                Some("hex") | Some("bighex") => {
+                    let big = self.next()? == "bighex";
+                    let x = self.register()?;
+                    self.instruction(if big {
+                        Instruction::_Fx30(x)
+                    } else {
+                        Instruction::_Fx29(x)
+                    })
+                }
+                Some("long") => {
+                    self.next()?;
+                    self.start(false)?;
+                    let label = self.next()?;
+                    match self.known_value(label)? {
+                        Some(address) => {
+                            let address = in_range(address, 0xFFFF)?;
+                            self.instruction(Instruction::_F000(address))
+                        }
+                        None => {
+                            self.fixups.push(Fixup {
+                                address: self.here + 2,
+                                label,
+                                line: self.line,
+                                long: true,
+                            });
+                            self.instruction(Instruction::_F000(0))
+                        }
+                    }
+                }
+                _ => self.address(Instruction::_Annn),
+            },
+            "+=" => {
+                let x = self.register()?;
+                self.instruction(Instruction::_Fx1E(x))
+            }
+            operator => Err(format!(
+                "expected `:=` or `+=` after `i`, got `{}`",
+                operator
+            )),
+        }
+    }
+
+    fn assign_register(&mut self, token: &str) -> Result<(), String> {
+        let x = self.register_named(token).unwrap_or_default();
+        let operator = self.next()?;
+        let instruction = match (operator, self.peek()) {
+            (":=", Some("delay")) => {
+                self.next()?;
+                Instruction::_Fx07(x)
+            }
+            (":=", Some("key")) => {
+                self.next()?;
+                Instruction::_Fx0A(x)
+            }
+            (":=", Some("random")) => {
+                self.next()?;
+                Instruction::_Cxkk(x, self.byte()?)
+            }
+            _ => match (operator, self.operand()?) {
+                (":=", Operand::Register(y)) => Instruction::_8xy0(x, y),
+                (":=", Operand::Value(kk)) => Instruction::_6xkk(x, kk),
+                ("+=", Operand::Register(y)) => Instruction::_8xy4(x, y),
+                ("+=", Operand::Value(kk)) => Instruction::_7xkk(x, kk),
+                ("-=", Operand::Register(y)) => Instruction::_8xy5(x, y),
+                ("-=", Operand::Value(kk)) => Instruction::_7xkk(x, kk.wrapping_neg()),
+                ("|=", Operand::Register(y)) => Instruction::_8xy1(x, y),
+                ("&=", Operand::Register(y)) => Instruction::_8xy2(x, y),
+                ("^=", Operand::Register(y)) => Instruction::_8xy3(x, y),
+                (">>=", Operand::Register(y)) => Instruction::_8xy6(x, y),
+                ("=-", Operand::Register(y)) => Instruction::_8xy7(x, y),
+                ("<<=", Operand::Register(y)) => Instruction::_8xyE(x, y),
+                ("|=" | "&=" | "^=" | ">>=" | "=-" | "<<=", Operand::Value(_)) => {
+                    return Err(format!("`{}` needs a register on the right", operator))
+                }
+                _ => return Err(format!("unknown operator `{}`", operator)),
+            },
+        };
+        self.instruction(instruction)
+    }
+
+    // Compiles an instruction that takes an address, which may be a label defined further on.
+    fn address(&mut self, instruction: fn(usize) -> Instruction) -> Result<(), String> {
+        self.start(false)?;
+        let label = self.next()?;
+        match self.known_value(label)? {
+            Some(address) => {
+                let address = in_range(address, 0xFFF)?;
+                self.instruction(instruction(address))
+            }
+            None => {
+                self.fixups.push(Fixup {
+                    address: self.here,
+                    label,
+                    line: self.line,
+                    long: false,
+                });
+                self.instruction(instruction(0))
+            }
+        }
+    }
+
+    // The value of a number, constant or label that is already defined.
+    fn known_value(&self, token: &str) -> Result<Option<i64>, String> {
+        if is_number(token) {
+            return number(token).map(Some);
+        }
+        if let Some(&value) = self.constants.get(token) {
+            return Ok(Some(value));
+        }
+        Ok(self.labels.get(token).map(|&address| address as i64))
+    }
+
+    fn value(&mut self) -> Result<i64, String> {
+        let token = self.next()?;
+        self.known_value(token)?
+            .ok_or_else(|| format!("unknown constant `{}`", token))
+    }
+
+    fn byte(&mut self) -> Result<u8, String> {
+        self.value().and_then(as_byte)
+    }
+
+    fn nibble(&mut self) -> Result<u8, String> {
+        self.value().and_then(|n| in_range(n, 0xF)).map(|n| n as u8)
+    }
+
+    fn operand(&mut self) -> Result<Operand, String> {
+        match self.peek().and_then(|token| self.register_named(token)) {
+            Some(register) => {
+                self.next()?;
+                Ok(Operand::Register(register))
+            }
+            None => self.byte().map(Operand::Value),
+        }
+    }
+
+    fn register(&mut self) -> Result<u8, String> {
+        let token = self.next()?;
+        self.register_named(token)
+            .ok_or_else(|| format!("expected a register, got `{}`", token))
+    }
+
+    fn register_named(&self, token: &str) -> Option<u8> {
+        if let Some(&register) = self.aliases.get(token) {
+            return Some(register);
+        }
+        let digit = token.strip_prefix(['v', 'V'])?;
+        if digit.len() != 1 {
+            return None;
+        }
+        u8::from_str_radix(digit, 16).ok()
+    }
+
+    fn name(&mut self) -> Result<&'a str, String> {
+        let name = self.next()?;
+        if is_number(name) || self.register_named(name).is_some() || name.starts_with(':') {
+            return Err(format!("`{}` cannot be used as a name", name));
+        }
+        Ok(name)
+    }
+
+    // Called before the first byte or label; programs with a main elsewhere start by jumping to it.
+    fn start(&mut self, main: bool) -> Result<(), String> {
+        if self.started {
+            return Ok(());
+        }
+        self.started = true;
+        if !main {
+            self.fixups.push(Fixup {
+                address: self.here,
+                label: "main",
+                line: self.line,
+                long: false,
+            });
+            self.emit(&Instruction::_1nnn(0).encode())?;
+        }
+        Ok(())
+    }
+
+    fn instruction(&mut self, instruction: Instruction) -> Result<(), String> {
+        self.emit(&instruction.encode())
+    }
+
+    // Emits a jump to be filled in later, returning its address.
+    fn placeholder(&mut self) -> Result<usize, String> {
+        self.start(false)?;
+        let address = self.here;
+        self.instruction(Instruction::_1nnn(0))?;
+        Ok(address)
+    }
+
+    // Points the jump at `address` to `target`.
+    fn patch(&mut self, address: usize, target: usize) -> Result<(), String> {
+        let target = in_range(target as i64, 0xFFF)?;
+        let offset = address - self.origin;
+        self.rom[offset] = (self.rom[offset] & 0xF0) | (target >> 8) as u8;
+        self.rom[offset + 1] = target as u8;
+        Ok(())
+    }
+
+    fn emit(&mut self, bytes: &[u8]) -> Result<(), String> {
+        self.start(false)?;
+        let offset = self.here - self.origin;
+        if self.rom.len() < offset + bytes.len() {
+            self.rom.resize(offset + bytes.len(), 0);
+        }
+        self.rom[offset..offset + bytes.len()].copy_from_slice(bytes);
+        self.here += bytes.len();
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<Vec<u8>, AssembleError> {
+        if let Some(block) = self.blocks.last() {
+            let (line, message) = match block {
+                Block::If { line, .. } | Block::Else { line, .. } => (*line, "`if ... begin`"),
+                Block::Loop { line, .. } => (*line, "`loop`"),
+            };
+            return Err(AssembleError {
+                line,
+                message: format!("{} is never closed", message),
+            });
+        }
+        for fixup in std::mem::take(&mut self.fixups) {
+            let error = |message: String| AssembleError {
+                line: fixup.line,
+                message,
+            };
+            let target = *self
+                .labels
+                .get(fixup.label)
+                .ok_or_else(|| error(format!("unknown label `{}`", fixup.label)))?;
+            if fixup.long {
+                let target = in_range(target as i64, 0xFFFF).map_err(error)?;
+                let offset = fixup.address - self.origin;
+                self.rom[offset..offset + 2].copy_from_slice(&(target as u16).to_be_bytes());
+            } else {
+                self.patch(fixup.address, target).map_err(error)?;
+            }
+        }
+        Ok(self.rom)
+    }
+}
+
+fn is_number(token: &str) -> bool {
+    token
+        .strip_prefix('-')
+        .unwrap_or(token)
+        .starts_with(|c: char| c.is_ascii_digit())
+}
+
+fn number(token: &str) -> Result<i64, String> {
+    let (negative, digits) = match token.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, token),
+    };
+    let lower = digits.to_ascii_lowercase();
+    let parsed = if let Some(hex) = lower.strip_prefix("0x") {
+        i64::from_str_radix(hex, 16)
+    } else if let Some(binary) = lower.strip_prefix("0b") {
+        i64::from_str_radix(binary, 2)
+    } else {
+        lower.parse()
+    };
+    let value = parsed.map_err(|_| format!("invalid number `{}`", token))?;
+    Ok(if negative { -value } else { value })
+}
+
+fn as_byte(value: i64) -> Result<u8, String> {
+    if (-128..=0xFF).contains(&value) {
+        Ok(value as u8)
+    } else {
+        Err(format!("{} does not fit in a byte", value))
+    }
+}
+
+fn in_range(value: i64, max: usize) -> Result<usize, String> {
+    usize::try_from(value)
+        .ok()
+        .filter(|&value| value <= max)
+        .ok_or_else(|| format!("{} is out of range, expected 0 to {:#X}", value, max))
+}
+
+// The instructions that leave VF at 1 if `first` is at least `second`, and at 0 otherwise.
+fn at_least(first: Operand, second: Operand) -> Vec<Instruction> {
+    match (first, second) {
+        (first, Operand::Register(y)) => {
+            let load = match first {
+                Operand::Register(x) => Instruction::_8xy0(0xF, x),
+                Operand::Value(kk) => Instruction::_6xkk(0xF, kk),
+            };
+            vec![load, Instruction::_8xy5(0xF, y)]
+        }
+        (Operand::Register(x), Operand::Value(kk)) => {
+            vec![Instruction::_6xkk(0xF, kk), Instruction::_8xy7(0xF, x)]
+        }
+        // the first operand of a comparison is always a register
+        (Operand::Value(_), Operand::Value(_)) => unreachable!(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::display::null_display::NullDisplay;
+    use crate::Chip8;
+
+    fn error(source: &str) -> String {
+        compile(source, 0x200).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn instructions() {
+        let rom = compile(
+            "
+            : main
+                clear
+                v1 := 0xAB  va += 2  vb -= 1  v3 <<= v4
+                i := long 0x1234
+                i := hex v2
+                sprite v0 v1 5
+                save v2 - v5
+                delay := v3
+                v4 := key
+                return
+            ",
+            0x200,
+        )
+        .unwrap();
+        assert_eq!(
+            rom,
+            [
+                0x00, 0xE0, 0x61, 0xAB, 0x7A, 0x02, 0x7B, 0xFF, 0x83, 0x4E, 0xF0, 0x00, 0x12, 0x34,
+                0xF2, 0x29, 0xD0, 0x15, 0x52, 0x52, 0xF3, 0x15, 0xF4, 0x0A, 0x00, 0xEE
+            ]
+        );
+    }
+
+    #[test]
+    fn labels_and_data() {
+        let rom = compile(
+            "
+            :alias x v3
+            :const HEIGHT 2
+            : ball
+                0b11000000 0xC0
+            : draw
+                i := ball
+                sprite x x HEIGHT
+            ;
+            : main
+                draw
+                jump main
+            ",
+            0x200,
+        )
+        .unwrap();
+        assert_eq!(
+            rom,
+            [
+                0x12, 0x0A, // jump main
+                0xC0, 0xC0, // ball
+                0xA2, 0x02, 0xD3, 0x32, 0x00, 0xEE, // draw
+                0x22, 0x04, 0x12, 0x0A, // main
+            ]
+        );
+    }
+
+    #[test]
+    fn control_flow() {
+        let rom = compile(
+            "
+            : main
+                loop
+                    if v0 == 3 begin
+                        v1 := 1
+                    else
+                        v1 := 2
+                    end
+                    while v2 key
+                    if v0 < v1 then v0 += 1
+                again
+            ",
+            0x200,
+        )
+        .unwrap();
+        assert_eq!(
+            rom,
+            [
+                0x30, 0x03, 0x12, 0x08, // if v0 == 3 begin
+                0x61, 0x01, 0x12, 0x0A, // then, and jump past else
+                0x61, 0x02, // else
+                0xE2, 0x9E, 0x12, 0x18, // while v2 key
+                0x8F, 0x00, 0x8F, 0x15, 0x4F, 0x00, // if v0 < v1
+                0x70, 0x01, 0x12, 0x00, // again
+            ]
+        );
+    }
+
+    #[test]
+    fn comparisons_run() {
+        // runs a program until it exits, and returns v2
+        let run = |source: &str| {
+            let rom = compile(source, 0x200).unwrap();
+            let mut display = NullDisplay::new();
+            let mut chip8 = Chip8::new(&mut display);
+            chip8.load_rom_bytes(&rom).unwrap();
+            for _ in 0..100 {
+                if chip8.is_halted() {
+                    return chip8.registers().read_vx(2);
+                }
+                chip8.step().unwrap();
+            }
+            panic!("{} did not exit", source);
+        };
+
+        let values: [u8; 3] = [1, 3, 5];
+        for operator in ["<", ">", "<=", ">="] {
+            for a in values {
+                for b in values {
+                    let expected = match operator {
+                        "<" => a < b,
+                        ">" => a > b,
+                        "<=" => a <= b,
+                        _ => a >= b,
+                    };
+                    let registers = format!(
+                        "v0 := {} v1 := {} if v0 {} v1 then v2 := 1 exit",
+                        a, b, operator
+                    );
+                    let constant =
+                        format!("v0 := {} if v0 {} {} then v2 := 1 exit", a, operator, b);
+                    for source in [registers, constant] {
+                        assert_eq!(run(&source), expected as u8, "{}", source);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn macros() {
+        let rom = compile(
+            "
+            :macro swap A B { vf := A A := B B := vf }
+            : main
+                swap v0 v1
+            ",
+            0x200,
+        )
+        .unwrap();
+        assert_eq!(rom, [0x8F, 0x00, 0x80, 0x10, 0x81, 0xF0]);
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            error(": main\n  jump nowhere"),
+            "line 2: unknown label `nowhere`"
+        );
+        assert_eq!(error("v0 := 256"), "line 1: 256 does not fit in a byte");
+        assert_eq!(error("\nloop\n v0 += 1"), "line 2: `loop` is never closed");
+        assert_eq!(
+            error("v0 |= 1"),
+            "line 1: `|=` needs a register on the right"
+        );
+        assert_eq!(
+            error(":calc x { 1 }"),
+            "line 1: unsupported directive `:calc`"
+        );
+        assert_eq!(
+            error(":macro m { m }\nm"),
+            "line 2: macro `m` expands without end"
+        );
+        assert_eq!(
+            error(": main\n:org 0x100"),
+            "line 2: cannot compile to 0x100, before the program"
+        );
+        assert_eq!(
+            error(": main\n:org 0xFFFFFFFFFF"),
+            "line 2: cannot compile to 0xFFFFFFFFFF, past the end of memory"
+        );
+    }
+}
@@ -0,0 +1,167 @@
+use std::str::FromStr;
+
+/// The colours pixels are drawn in: off, on in the first plane, on in the second plane and on in
+/// both. Chip-8 and SUPER-CHIP programs only use the first two.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Palette {
+    colours: [[u8; 3]; 4],
+}
+
+impl Palette {
+    /// Green phosphor on black, with amber and white for XO-CHIP's extra colours.
+    pub const GREEN: Palette = Palette::new([
+        [0x00, 0x00, 0x00],
+        [0x00, 0xFF, 0x00],
+        [0xFF, 0xAA, 0x00],
+        [0xFF, 0xFF, 0xFF],
+    ]);
+    /// Amber phosphor on black.
+    pub const AMBER: Palette = Palette::new([
+        [0x00, 0x00, 0x00],
+        [0xFF, 0xB0, 0x00],
+        [0x80, 0x58, 0x00],
+        [0xFF, 0xE0, 0x90],
+    ]);
+    /// Shades of green, like an early handheld's LCD.
+    pub const LCD: Palette = Palette::new([
+        [0x9B, 0xBC, 0x0F],
+        [0x0F, 0x38, 0x0F],
+        [0x8B, 0xAC, 0x0F],
+        [0x30, 0x62, 0x30],
+    ]);
+    /// White on black, with yellow and cyan for XO-CHIP's extra colours.
+    pub const HIGH_CONTRAST: Palette = Palette::new([
+        [0x00, 0x00, 0x00],
+        [0xFF, 0xFF, 0xFF],
+        [0xFF, 0xFF, 0x00],
+        [0x00, 0xFF, 0xFF],
+    ]);
+    /// Octo's default colours, which many XO-CHIP games are designed for.
+    pub const OCTO: Palette = Palette::new([
+        [0x99, 0x66, 0x00],
+        [0xFF, 0xCC, 0x00],
+        [0xFF, 0x66, 0x00],
+        [0x66, 0x22, 0x00],
+    ]);
+
+    pub const PRESETS: [&'static str; 5] = ["green", "amber", "lcd", "contrast", "octo"];
+    const PRESET_PALETTES: [Palette; 5] = [
+        Palette::GREEN,
+        Palette::AMBER,
+        Palette::LCD,
+        Palette::HIGH_CONTRAST,
+        Palette::OCTO,
+    ];
+
+    pub const fn new(colours: [[u8; 3]; 4]) -> Self {
+        Palette { colours }
+    }
+
+    /// The RGB colour of a pixel colour (0 - 3).
+    pub fn colour(&self, colour: u8) -> [u8; 3] {
+        self.colours[colour as usize]
+    }
+
+    pub fn colours(&self) -> &[[u8; 3]; 4] {
+        &self.colours
+    }
+
+    /// The preset after this one, wrapping around; the first preset if this is not a preset.
+    pub fn next_preset(&self) -> Palette {
+        let presets = Palette::PRESET_PALETTES;
+        match presets.iter().position(|preset| preset == self) {
+            Some(i) => presets[(i + 1) % presets.len()],
+            None => presets[0],
+        }
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Palette::GREEN
+    }
+}
+
+/// A preset name, or a background and foreground colour as `RRGGBB,RRGGBB`, optionally followed by
+/// the colours for XO-CHIP's second plane and for both planes. Without them, the second plane is
+/// drawn in the foreground colour too.
+impl FromStr for Palette {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(i) = Palette::PRESETS.iter().position(|&name| name == s) {
+            return Ok(Palette::PRESET_PALETTES[i]);
+        }
+        let invalid = || {
+            format!(
+                "unknown palette '{}', expected one of: {}, or colours as RRGGBB,RRGGBB[,RRGGBB,RRGGBB]",
+                s,
+                Palette::PRESETS.join(", ")
+            )
+        };
+        let colours = s
+            .split(',')
+            .map(|colour| parse_colour(colour).ok_or_else(invalid))
+            .collect::<Result<Vec<[u8; 3]>, String>>()?;
+        match colours[..] {
+            [background, foreground] => Ok(Palette::new([
+                background, foreground, foreground, foreground,
+            ])),
+            [background, first, second, both] => {
+                Ok(Palette::new([background, first, second, both]))
+            }
+            _ => Err(invalid()),
+        }
+    }
+}
+
+// RRGGBB, with an optional leading #.
+fn parse_colour(s: &str) -> Option<[u8; 3]> {
+    let hex = s.trim().trim_start_matches('#');
+    if hex.len() != 6 || !hex.is_ascii() {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    Some([channel(0)?, channel(2)?, channel(4)?])
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn presets() {
+        for name in Palette::PRESETS {
+            assert!(name.parse::<Palette>().is_ok());
+        }
+        assert_eq!("amber".parse(), Ok(Palette::AMBER));
+        assert_eq!(Palette::default(), Palette::GREEN);
+    }
+
+    #[test]
+    fn custom() {
+        assert_eq!(
+            "#102030,ffffff".parse::<Palette>().unwrap().colours(),
+            &[[0x10, 0x20, 0x30], [0xFF; 3], [0xFF; 3], [0xFF; 3]]
+        );
+        assert_eq!(
+            "000000,111111,222222,333333"
+                .parse::<Palette>()
+                .unwrap()
+                .colour(2),
+            [0x22; 3]
+        );
+        assert!("000000".parse::<Palette>().is_err());
+        assert!("000000,12345g".parse::<Palette>().is_err());
+        assert!("000000,111111,222222".parse::<Palette>().is_err());
+        assert!("teal".parse::<Palette>().is_err());
+    }
+
+    #[test]
+    fn next_preset() {
+        assert_eq!(Palette::GREEN.next_preset(), Palette::AMBER);
+        assert_eq!(Palette::OCTO.next_preset(), Palette::GREEN);
+        let custom: Palette = "000000,ffffff".parse().unwrap();
+        assert_eq!(custom.next_preset(), Palette::GREEN);
+    }
+}
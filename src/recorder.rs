@@ -0,0 +1,309 @@
+use std::borrow::Cow;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use crate::display::chip8_display::{pixel_colour, Chip8Display};
+use crate::machine::chip8::{DISPLAY_COLS, DISPLAY_ROWS};
+use crate::machine::display_state::{DisplayState, PLANES};
+use crate::palette::Palette;
+use crate::screenshot;
+
+// GIF frame delays are in hundredths of a second, and most viewers slow down frames that are
+// shorter than 2/100 s, so no frame is shown for less than that.
+const MIN_GIF_DELAY: usize = 2;
+
+#[derive(Clone)]
+struct Frame {
+    cols: usize,
+    rows: usize,
+    // the colour (0 - 3) of every pixel
+    pixels: Vec<u8>,
+}
+
+/// Records presented frames, one per 60 Hz frame, and saves them as an animated GIF or a
+/// sequence of PNGs.
+///
+/// A `Recorder` can be used as the [`Chip8Display`] itself, in which case it records every
+/// frame drawn while recording is on, or fed frames with [`Recorder::capture`] alongside another
+/// display. Frames are kept in memory until they are saved.
+pub struct Recorder {
+    palette: Palette,
+    recording: bool,
+    cols: usize,
+    rows: usize,
+    frames: Vec<Frame>,
+}
+
+impl Recorder {
+    /// A recorder that saves frames in the colours of `palette`. It does not record until
+    /// started.
+    pub fn new(palette: Palette) -> Self {
+        Recorder {
+            palette,
+            recording: false,
+            cols: DISPLAY_COLS,
+            rows: DISPLAY_ROWS,
+            frames: vec![],
+        }
+    }
+
+    /// Start recording, discarding any frames recorded before.
+    pub fn start(&mut self) {
+        self.frames.clear();
+        self.recording = true;
+    }
+
+    /// Stop recording, keeping the recorded frames to be saved.
+    pub fn stop(&mut self) {
+        self.recording = false;
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording
+    }
+
+    /// The number of frames recorded.
+    pub fn frames(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Record the current frame of `state`, if recording.
+    pub fn capture(&mut self, state: &DisplayState) {
+        if self.recording {
+            self.push(state.cols(), state.rows(), |x, y| state.pixel(x, y));
+        }
+    }
+
+    fn push(&mut self, cols: usize, rows: usize, pixel: impl Fn(usize, usize) -> u8) {
+        let pixels = (0..rows)
+            .flat_map(|y| (0..cols).map(move |x| (x, y)))
+            .map(|(x, y)| pixel(x, y))
+            .collect();
+        self.frames.push(Frame { cols, rows, pixels });
+    }
+
+    /// Save the recording as an animated GIF if `path` ends in `.gif`, or otherwise as numbered
+    /// PNGs in the directory `path`, with every pixel scaled up to a `scale` x `scale` square.
+    pub fn save(&self, path: &Path, scale: usize) -> io::Result<()> {
+        let is_gif = path
+            .extension()
+            .is_some_and(|e| e.eq_ignore_ascii_case("gif"));
+        if is_gif {
+            let mut out = BufWriter::new(File::create(path)?);
+            self.write_gif(&mut out, scale)?;
+            out.flush()
+        } else {
+            self.write_png_sequence(path, scale)
+        }
+    }
+
+    /// Write the recording as a looping GIF. Frames that are not different from the one before
+    /// are merged, and frames that would be on screen for less than 2/100 s are dropped, so the
+    /// animation plays at the speed it was recorded. The GIF is as large as the largest frame;
+    /// low-res frames are scaled up to fill it.
+    pub fn write_gif(&self, out: &mut dyn Write, scale: usize) -> io::Result<()> {
+        let cols = self.frames.iter().map(|f| f.cols).max().unwrap_or(0);
+        let rows = self.frames.iter().map(|f| f.rows).max().unwrap_or(0);
+        if self.frames.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "no frames were recorded",
+            ));
+        }
+        let (width, height) = (cols * scale, rows * scale);
+
+        // the time in hundredths of a second at which each frame is shown, and its index
+        let mut shown: Vec<(usize, usize)> = vec![];
+        for (i, frame) in self.frames.iter().enumerate() {
+            let time = (i * 100 + 30) / 60;
+            match shown.last() {
+                Some(&(_, last)) if self.frames[last].pixels == frame.pixels => {}
+                Some(&(start, _)) if time < start + MIN_GIF_DELAY => {}
+                _ => shown.push((time, i)),
+            }
+        }
+        let end = (self.frames.len() * 100 + 30) / 60;
+
+        let palette = self.palette.colours().concat();
+        let mut encoder =
+            gif::Encoder::new(out, width as u16, height as u16, &palette).map_err(gif_error)?;
+        encoder
+            .set_repeat(gif::Repeat::Infinite)
+            .map_err(gif_error)?;
+        for (n, &(start, i)) in shown.iter().enumerate() {
+            let next = shown.get(n + 1).map_or(end, |&(time, _)| time);
+            let frame = &self.frames[i];
+            let frame_scale = (cols / frame.cols) * scale;
+            let buffer = (0..height)
+                .flat_map(|y| (0..width).map(move |x| (x, y)))
+                .map(|(x, y)| frame.pixels[y / frame_scale * frame.cols + x / frame_scale])
+                .collect::<Vec<u8>>();
+            let gif_frame = gif::Frame {
+                width: width as u16,
+                height: height as u16,
+                delay: (next - start).max(MIN_GIF_DELAY) as u16,
+                buffer: Cow::Owned(buffer),
+                ..gif::Frame::default()
+            };
+            encoder.write_frame(&gif_frame).map_err(gif_error)?;
+        }
+        encoder.into_inner()?;
+        Ok(())
+    }
+
+    /// Write every recorded frame as a PNG named `frame_00000.png`, `frame_00001.png`, ... in
+    /// `dir`, creating it if needed. Each file is one 60 Hz frame.
+    pub fn write_png_sequence(&self, dir: &Path, scale: usize) -> io::Result<()> {
+        fs::create_dir_all(dir)?;
+        for (i, frame) in self.frames.iter().enumerate() {
+            let path = dir.join(format!("frame_{:05}.png", i));
+            let mut out = BufWriter::new(File::create(path)?);
+            screenshot::encode_png(
+                frame.cols,
+                frame.rows,
+                scale,
+                &self.palette,
+                |x, y| frame.pixels[y * frame.cols + x],
+                &mut out,
+            )?;
+            out.flush()?;
+        }
+        Ok(())
+    }
+}
+
+impl Chip8Display for Recorder {
+    fn draw(&mut self, planes: [&[u8]; PLANES]) {
+        if !self.recording {
+            return;
+        }
+        let cols = self.cols;
+        self.push(self.cols, self.rows, |x, y| {
+            pixel_colour(planes, cols, x, y)
+        });
+    }
+
+    fn repeat_frame(&mut self) {
+        if let Some(last) = self.frames.last().filter(|_| self.recording) {
+            self.frames.push(last.clone());
+        }
+    }
+
+    fn resize(&mut self, cols: usize, rows: usize) {
+        self.cols = cols;
+        self.rows = rows;
+    }
+}
+
+fn gif_error(e: gif::EncodingError) -> io::Error {
+    match e {
+        gif::EncodingError::Io(e) => e,
+        e => io::Error::new(io::ErrorKind::InvalidData, e),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn decode(gif: &[u8]) -> Vec<(u16, u16, u16, Vec<u8>)> {
+        let mut options = gif::DecodeOptions::new();
+        options.set_color_output(gif::ColorOutput::Indexed);
+        let mut decoder = options.read_info(gif).unwrap();
+        let mut frames = vec![];
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            frames.push((
+                frame.width,
+                frame.height,
+                frame.delay,
+                frame.buffer.to_vec(),
+            ));
+        }
+        frames
+    }
+
+    #[test]
+    fn records_only_while_started() {
+        let mut recorder = Recorder::new(Palette::default());
+        let state = DisplayState::new(64, 32);
+        recorder.draw(state.planes());
+        assert_eq!(recorder.frames(), 0);
+
+        recorder.start();
+        recorder.draw(state.planes());
+        recorder.capture(&state);
+        recorder.repeat_frame();
+        recorder.stop();
+        recorder.draw(state.planes());
+        recorder.repeat_frame();
+        assert_eq!(recorder.frames(), 3);
+    }
+
+    #[test]
+    fn draw_and_capture_agree() {
+        let mut state = DisplayState::new(64, 32);
+        state.flip(0, 3, 1);
+        state.flip(1, 3, 1);
+        state.flip(1, 63, 31);
+        let mut recorder = Recorder::new(Palette::default());
+        recorder.start();
+        recorder.draw(state.planes());
+        recorder.capture(&state);
+
+        assert_eq!(recorder.frames[0].pixels, recorder.frames[1].pixels);
+        assert_eq!(recorder.frames[0].pixels[64 + 3], 3);
+        assert_eq!(recorder.frames[0].pixels[64 * 32 - 1], 2);
+    }
+
+    #[test]
+    fn gif_timing() {
+        let mut recorder = Recorder::new(Palette::default());
+        recorder.start();
+        let mut state = DisplayState::new(64, 32);
+        for i in 0..6 {
+            state.flip(0, i, 0);
+            recorder.capture(&state);
+        }
+        // the last frame stays on screen for a second
+        for _ in 0..60 {
+            recorder.capture(&state);
+        }
+        let mut out = vec![];
+        recorder.write_gif(&mut out, 1).unwrap();
+
+        let delays: Vec<u16> = decode(&out).iter().map(|f| f.2).collect();
+        // frames are shown at 0, 2, 3, 5, 7 and 8 hundredths of a second; those at 3 and 8 come
+        // too soon after the one before and are dropped
+        assert_eq!(delays, [2, 3, 2, 3, 100]);
+        assert_eq!(delays.iter().sum::<u16>(), 110);
+    }
+
+    #[test]
+    fn gif_scales_low_res_frames() {
+        let mut recorder = Recorder::new(Palette::default());
+        recorder.start();
+        let mut state = DisplayState::new(64, 32);
+        state.flip(0, 1, 0);
+        recorder.capture(&state);
+        state.resize(128, 64);
+        recorder.capture(&state);
+        let mut out = vec![];
+        recorder.write_gif(&mut out, 1).unwrap();
+
+        let frames = decode(&out);
+        assert_eq!(frames.len(), 2);
+        let (width, height, _, pixels) = &frames[0];
+        assert_eq!((*width, *height), (128, 64));
+        assert_eq!(&pixels[..4], &[0, 0, 1, 1]);
+        assert_eq!(&pixels[128..132], &[0, 0, 1, 1]);
+        assert!(frames[1].3.iter().all(|&p| p == 0));
+    }
+
+    #[test]
+    fn gif_without_frames() {
+        let recorder = Recorder::new(Palette::default());
+        let result = recorder.write_gif(&mut vec![], 1);
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidInput);
+    }
+}
@@ -0,0 +1,185 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use crate::machine::display_state::DisplayState;
+use crate::palette::Palette;
+
+/// Save the framebuffer as an image, with every pixel scaled up to a `scale` x `scale` square.
+/// The format follows from the extension of `path`: `png`, `pbm` or `pgm`.
+pub fn save(state: &DisplayState, path: &Path, scale: usize, palette: &Palette) -> io::Result<()> {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase());
+    let mut out = match extension.as_deref() {
+        Some("png" | "pbm" | "pgm") => BufWriter::new(File::create(path)?),
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{}: expected a .png, .pbm or .pgm file", path.display()),
+            ))
+        }
+    };
+    match extension.as_deref() {
+        Some("png") => write_png(state, scale, palette, &mut out)?,
+        Some("pbm") => write_pbm(state, scale, &mut out)?,
+        _ => write_pgm(state, scale, palette, &mut out)?,
+    }
+    out.flush()
+}
+
+/// Write the framebuffer as an RGB PNG.
+pub fn write_png(
+    state: &DisplayState,
+    scale: usize,
+    palette: &Palette,
+    out: &mut dyn Write,
+) -> io::Result<()> {
+    let (cols, rows) = (state.cols(), state.rows());
+    encode_png(cols, rows, scale, palette, |x, y| state.pixel(x, y), out)
+}
+
+// Writes a cols x rows image as an RGB PNG, scaled up, looking up the colour of every pixel in
+// `palette`.
+pub(crate) fn encode_png(
+    cols: usize,
+    rows: usize,
+    scale: usize,
+    palette: &Palette,
+    pixel: impl Fn(usize, usize) -> u8,
+    out: &mut dyn Write,
+) -> io::Result<()> {
+    let (width, height) = (cols * scale, rows * scale);
+    let mut encoder = png::Encoder::new(out, width as u32, height as u32);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+
+    let mut data = Vec::with_capacity(width * height * 3);
+    for y in 0..height {
+        for x in 0..width {
+            data.extend_from_slice(&palette.colour(pixel(x / scale, y / scale)));
+        }
+    }
+    writer.write_image_data(&data)?;
+    Ok(writer.finish()?)
+}
+
+/// Write the framebuffer as a binary (P4) PBM. PBM has no colour: pixels that are on in any plane
+/// are black, the rest white.
+pub fn write_pbm(state: &DisplayState, scale: usize, out: &mut dyn Write) -> io::Result<()> {
+    let (width, height) = (state.cols() * scale, state.rows() * scale);
+    writeln!(out, "P4\n{} {}", width, height)?;
+
+    // every row is padded to a whole number of bytes
+    let row_bytes = width.div_ceil(8);
+    for y in 0..height {
+        let mut row = vec![0x00; row_bytes];
+        for x in 0..width {
+            if state.pixel(x / scale, y / scale) != 0 {
+                row[x / 8] |= 0b10000000 >> (x % 8);
+            }
+        }
+        out.write_all(&row)?;
+    }
+    Ok(())
+}
+
+/// Write the framebuffer as a binary (P5) PGM, using the luma of each palette colour.
+pub fn write_pgm(
+    state: &DisplayState,
+    scale: usize,
+    palette: &Palette,
+    out: &mut dyn Write,
+) -> io::Result<()> {
+    let (width, height) = (state.cols() * scale, state.rows() * scale);
+    writeln!(out, "P5\n{} {}\n255", width, height)?;
+
+    let greys = palette
+        .colours()
+        .map(|[r, g, b]| ((r as u32 * 299 + g as u32 * 587 + b as u32 * 114) / 1000) as u8);
+    let mut data = Vec::with_capacity(width * height);
+    for y in 0..height {
+        for x in 0..width {
+            data.push(greys[state.pixel(x / scale, y / scale) as usize]);
+        }
+    }
+    out.write_all(&data)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn state() -> DisplayState {
+        // 16x2, with the top left pixel on in plane 0 and the bottom right in both planes
+        let mut state = DisplayState::new(16, 2);
+        state.flip(0, 0, 0);
+        state.flip(0, 15, 1);
+        state.flip(1, 15, 1);
+        state
+    }
+
+    #[test]
+    fn pbm() {
+        let mut out = vec![];
+        write_pbm(&state(), 1, &mut out).unwrap();
+
+        let mut expected = b"P4\n16 2\n".to_vec();
+        expected.extend([0b10000000, 0x00, 0x00, 0b00000001]);
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn pbm_scaled() {
+        let mut out = vec![];
+        write_pbm(&state(), 3, &mut out).unwrap();
+
+        let header = b"P4\n48 6\n";
+        assert_eq!(&out[..header.len()], header);
+        let data = &out[header.len()..];
+        assert_eq!(data.len(), 6 * 6);
+        assert_eq!(&data[..6], &[0b11100000, 0, 0, 0, 0, 0]);
+        assert_eq!(&data[30..], &[0, 0, 0, 0, 0, 0b00000111]);
+    }
+
+    #[test]
+    fn pgm() {
+        let mut out = vec![];
+        write_pgm(&state(), 1, &Palette::GREEN, &mut out).unwrap();
+
+        let header = b"P5\n16 2\n255\n";
+        assert_eq!(&out[..header.len()], header);
+        let data = &out[header.len()..];
+        assert_eq!(data.len(), 32);
+        assert_eq!(data[0], 149);
+        assert_eq!(data[1], 0);
+        assert_eq!(data[31], 255);
+    }
+
+    #[test]
+    fn png() {
+        let mut out = vec![];
+        write_png(&state(), 2, &Palette::GREEN, &mut out).unwrap();
+
+        let decoder = png::Decoder::new(out.as_slice());
+        let mut reader = decoder.read_info().unwrap();
+        let mut data = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut data).unwrap();
+        assert_eq!((info.width, info.height), (32, 4));
+        assert_eq!(info.color_type, png::ColorType::Rgb);
+
+        let pixel = |x: usize, y: usize| &data[(y * 32 + x) * 3..(y * 32 + x) * 3 + 3];
+        assert_eq!(pixel(0, 0), Palette::GREEN.colours()[1]);
+        assert_eq!(pixel(1, 1), Palette::GREEN.colours()[1]);
+        assert_eq!(pixel(2, 0), Palette::GREEN.colours()[0]);
+        assert_eq!(pixel(31, 3), Palette::GREEN.colours()[3]);
+    }
+
+    #[test]
+    fn save_unknown_format() {
+        let result = save(&state(), Path::new("frame.bmp"), 1, &Palette::GREEN);
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidInput);
+    }
+}